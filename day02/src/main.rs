@@ -1,33 +1,32 @@
 use std::{env::args, fs};
 
-fn main() {
-    let mut args = args();
-    args.next();
-    let input_file_path = args
-        .next()
-        .expect("Input file path expected as first argument");
-    let input_file_content = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {}", input_file_path));
+const COLORS: [&str; 3] = ["red", "green", "blue"];
+const PART_1_BAG: [i32; 3] = [12, 13, 14];
 
-    let mut part_1_result = 0;
-    let mut part_2_result = 0;
+#[derive(Debug)]
+struct Game {
+    id: i32,
+    // RGB counts of each draw, in input order
+    draws: Vec<[i32; 3]>,
+}
 
-    for line in input_file_content.lines() {
+impl Game {
+    fn new(line: &str) -> Self {
         let split = line.split(": ").collect::<Vec<_>>();
         assert!(split.len() == 2);
 
-        let game_id: i32 = split[0]
+        let id: i32 = split[0]
             .split(' ')
-            .last()
+            .next_back()
             .expect("Game id must not be empty")
             .parse()
             .expect("Game id must be an integer");
-        let draws = split[1];
 
-        // RGB counts
-        let mut counts = vec![0, 0, 0];
+        let mut draws = vec![];
+
+        for draw in split[1].split("; ") {
+            let mut counts = [0, 0, 0];
 
-        for draw in draws.split("; ") {
             for die in draw.split(", ") {
                 let die = die.split(' ').collect::<Vec<_>>();
                 assert!(die.len() == 2);
@@ -37,24 +36,118 @@ fn main() {
                     .expect("First part of dice draw must be an integer");
                 let color = die[1];
 
-                let color_index = match color {
-                    "red" => 0,
-                    "green" => 1,
-                    "blue" => 2,
-                    other => panic!("Unexpected color {}", other),
-                };
+                let color_index = COLORS
+                    .iter()
+                    .position(|e| *e == color)
+                    .unwrap_or_else(|| panic!("Unexpected color {}", color));
 
-                counts[color_index] = counts[color_index].max(count);
+                counts[color_index] += count;
             }
-        }
 
-        if counts[0] <= 12 && counts[1] <= 13 && counts[2] <= 14 {
-            part_1_result += game_id;
+            draws.push(counts);
         }
 
-        part_2_result += counts.iter().fold(1, |acc, e| acc * e);
+        Self { id, draws }
     }
 
-    println!("Part 1 result {}", part_1_result);
-    println!("Part 2 result {}", part_2_result);
+    /// Smallest bag this game is possible with.
+    fn max_counts(&self) -> [i32; 3] {
+        self.draws.iter().fold([0, 0, 0], |acc, e| {
+            [acc[0].max(e[0]), acc[1].max(e[1]), acc[2].max(e[2])]
+        })
+    }
+
+    /// Index and counts of the first draw that does not fit into `bag`.
+    fn first_violation(&self, bag: &[i32; 3]) -> Option<(usize, [i32; 3])> {
+        self.draws
+            .iter()
+            .enumerate()
+            .find(|(_, draw)| draw.iter().zip(bag).any(|(count, limit)| count > limit))
+            .map(|(i, draw)| (i, *draw))
+    }
+}
+
+fn minimal_bag(games: &[Game], ids: &[i32]) -> [i32; 3] {
+    ids.iter()
+        .map(|id| {
+            games
+                .iter()
+                .find(|e| e.id == *id)
+                .unwrap_or_else(|| panic!("Game {} not found", id))
+                .max_counts()
+        })
+        .fold([0, 0, 0], |acc, e| {
+            [acc[0].max(e[0]), acc[1].max(e[1]), acc[2].max(e[2])]
+        })
+}
+
+fn format_counts(counts: &[i32; 3]) -> String {
+    counts
+        .iter()
+        .zip(COLORS)
+        .map(|(count, color)| format!("{} {}", count, color))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn main() {
+    let mut args = args();
+    args.next();
+    let input_file_path = args
+        .next()
+        .expect("Input file path expected as first argument");
+    let input_file_content = fs::read_to_string(&input_file_path)
+        .unwrap_or_else(|_| panic!("Could not open input file {}", input_file_path));
+
+    let games: Vec<_> = input_file_content.lines().map(Game::new).collect();
+
+    match args.next().as_deref() {
+        None => {
+            let part_1_result: i32 = games
+                .iter()
+                .filter(|e| e.first_violation(&PART_1_BAG).is_none())
+                .map(|e| e.id)
+                .sum();
+            let part_2_result: i32 = games
+                .iter()
+                .map(|e| e.max_counts().iter().product::<i32>())
+                .sum();
+
+            println!("Part 1 result {}", part_1_result);
+            println!("Part 2 result {}", part_2_result);
+        }
+        Some("min-bag") => {
+            let ids: Vec<i32> = match args.next() {
+                Some(ids) => ids
+                    .split(',')
+                    .map(|e| e.parse().expect("Game ids must be integers"))
+                    .collect(),
+                None => games.iter().map(|e| e.id).collect(),
+            };
+
+            let bag = minimal_bag(&games, &ids);
+
+            println!("Minimal bag {}", format_counts(&bag));
+        }
+        Some("check") => {
+            let bag: Vec<i32> = args
+                .map(|e| e.parse().expect("Bag counts must be integers"))
+                .collect();
+            let bag: [i32; 3] = bag
+                .try_into()
+                .expect("Expected red, green and blue bag counts");
+
+            for game in &games {
+                if let Some((draw, counts)) = game.first_violation(&bag) {
+                    println!(
+                        "Game {} impossible, draw {}: {}",
+                        game.id,
+                        draw + 1,
+                        format_counts(&counts)
+                    );
+                }
+            }
+        }
+        Some(other) => panic!("Unexpected mode {}", other),
+    }
 }
@@ -1,135 +1,350 @@
-use std::{env, fs, vec};
+use std::{collections::BTreeMap, env, fs};
 
-fn check_symbol_safe(schema: &Vec<Vec<char>>, row: usize, col: usize) -> bool {
-    let Some(row) = schema.get(row) else {
-        return false;
-    };
+#[derive(Debug)]
+struct Number {
+    value: i32,
+    row: usize,
+    // inclusive column span
+    start: usize,
+    end: usize,
+}
 
-    let Some(cell) = row.get(col) else {
-        return false;
-    };
+#[derive(Debug)]
+struct Symbol {
+    symbol: char,
+    row: usize,
+    col: usize,
+}
 
-    *cell != '.' && !cell.is_numeric()
+impl Number {
+    fn touches(&self, symbol: &Symbol) -> bool {
+        self.row.abs_diff(symbol.row) <= 1
+            && self.start.saturating_sub(1) <= symbol.col
+            && symbol.col <= self.end + 1
+    }
 }
 
-fn main() {
-    let mut args = env::args();
-    args.next();
-    let input_file_path = args.next().expect("Input file path");
+#[derive(Debug)]
+struct Schematic {
+    numbers: Vec<Number>,
+    symbols: Vec<Symbol>,
+    // indices into `numbers` and `symbols`, per row
+    numbers_by_row: Vec<Vec<usize>>,
+    symbols_by_row: Vec<Vec<usize>>,
+}
 
-    let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}."));
+impl Schematic {
+    fn new(input: &str) -> Self {
+        let mut numbers = vec![];
+        let mut symbols = vec![];
+        let mut numbers_by_row = vec![];
+        let mut symbols_by_row = vec![];
 
-    let mut schema: Vec<_> = vec![];
-    for line in input.lines() {
-        schema.push(line.chars().collect::<Vec<_>>());
-    }
+        for (row, line) in input.lines().enumerate() {
+            let mut numbers_row = vec![];
+            let mut symbols_row = vec![];
+            let mut number: Option<Number> = None;
 
-    let mut part_1_result = 0;
-
-    let mut parsed_numbers: Vec<Vec<(i32, usize, usize)>> = vec![];
-    let mut parsed_gears: Vec<(usize, usize)> = vec![];
-
-    for i in 0..schema.len() {
-        let mut number: Option<(i32, usize)> = None;
-        let mut symbol_found = false;
-
-        let mut parsed_numbers_row = vec![];
-
-        for j in 0..schema[i].len() {
-            if schema[i][j].is_numeric() {
-                if let Some((value, start_position)) = number {
-                    number = Some((
-                        value * 10 + (schema[i][j] as u8 - '0' as u8) as i32,
-                        start_position,
-                    ));
-                } else {
-                    number = Some((
-                        (schema[i][j] as u8 - '0' as u8) as i32,
-                        if j > 0 { j - 1 } else { j },
-                    ));
-
-                    if j > 0 {
-                        if i > 0 {
-                            symbol_found = symbol_found || check_symbol_safe(&schema, i - 1, j - 1);
+            for (col, c) in line.chars().enumerate() {
+                if let Some(digit) = c.to_digit(10) {
+                    match &mut number {
+                        Some(number) => {
+                            number.value = number.value * 10 + digit as i32;
+                            number.end = col;
+                        }
+                        None => {
+                            number = Some(Number {
+                                value: digit as i32,
+                                row,
+                                start: col,
+                                end: col,
+                            })
                         }
-                        symbol_found = symbol_found
-                            || check_symbol_safe(&schema, i, j - 1)
-                            || check_symbol_safe(&schema, i + 1, j - 1);
-                    }
-                }
-                if number.is_some() && !symbol_found {
-                    if i > 0 {
-                        symbol_found = symbol_found || check_symbol_safe(&schema, i - 1, j);
                     }
-                    symbol_found = symbol_found || check_symbol_safe(&schema, i + 1, j);
-                }
-            } else {
-                if schema[i][j] == '*' {
-                    parsed_gears.push((i, j));
+                    continue;
                 }
-                if number.is_some() && !symbol_found {
-                    if i > 0 {
-                        symbol_found = symbol_found || check_symbol_safe(&schema, i - 1, j);
-                    }
-                    symbol_found = symbol_found
-                        || check_symbol_safe(&schema, i, j)
-                        || check_symbol_safe(&schema, i + 1, j);
+
+                if let Some(number) = number.take() {
+                    numbers_row.push(numbers.len());
+                    numbers.push(number);
                 }
-                if symbol_found {
-                    if let Some((value, start_position)) = number {
-                        part_1_result += value;
 
-                        parsed_numbers_row.push((value, start_position, j));
-                    }
+                if c != '.' {
+                    symbols_row.push(symbols.len());
+                    symbols.push(Symbol {
+                        symbol: c,
+                        row,
+                        col,
+                    });
                 }
+            }
 
-                number = None;
-                symbol_found = false;
+            if let Some(number) = number {
+                numbers_row.push(numbers.len());
+                numbers.push(number);
             }
+
+            numbers_by_row.push(numbers_row);
+            symbols_by_row.push(symbols_row);
+        }
+
+        Self {
+            numbers,
+            symbols,
+            numbers_by_row,
+            symbols_by_row,
+        }
+    }
+
+    fn numbers_touching<'a>(&'a self, symbol: &'a Symbol) -> impl Iterator<Item = &'a Number> {
+        self.neighbour_rows(&self.numbers_by_row, symbol.row)
+            .map(|i| &self.numbers[*i])
+            .filter(|e| e.touches(symbol))
+    }
+
+    fn symbols_touching<'a>(&'a self, number: &'a Number) -> impl Iterator<Item = &'a Symbol> {
+        self.neighbour_rows(&self.symbols_by_row, number.row)
+            .map(|i| &self.symbols[*i])
+            .filter(|e| number.touches(e))
+    }
+
+    fn neighbour_rows<'a>(
+        &self,
+        by_row: &'a [Vec<usize>],
+        row: usize,
+    ) -> impl Iterator<Item = &'a usize> {
+        by_row[row.saturating_sub(1)..(row + 2).min(by_row.len())]
+            .iter()
+            .flatten()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Aggregate {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Aggregate {
+    fn new(name: &str) -> Self {
+        match name {
+            "product" => Self::Product,
+            "sum" => Self::Sum,
+            "max" => Self::Max,
+            _ => panic!("Unexpected aggregate {name}"),
+        }
+    }
+
+    fn apply(&self, values: &[i32]) -> i32 {
+        match self {
+            Aggregate::Product => values.iter().product(),
+            Aggregate::Sum => values.iter().sum(),
+            Aggregate::Max => values.iter().copied().max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GearRule {
+    symbol: char,
+    // exact number of adjacent numbers required
+    count: usize,
+    aggregate: Aggregate,
+}
+
+const PART_2_GEAR_RULE: GearRule = GearRule {
+    symbol: '*',
+    count: 2,
+    aggregate: Aggregate::Product,
+};
+
+impl GearRule {
+    /// Parses a rule in the `symbol:count:aggregate` format, e.g. `*:2:product`.
+    fn new(rule: &str) -> Self {
+        let parts: Vec<_> = rule.split(':').collect();
+        let [symbol, count, aggregate] = parts[..] else {
+            panic!("Expected gear rule in symbol:count:aggregate format, got {rule}");
+        };
+
+        let mut symbol = symbol.chars();
+        let (Some(symbol), None) = (symbol.next(), symbol.next()) else {
+            panic!("Expected gear symbol to be a single character in {rule}");
+        };
+
+        Self {
+            symbol,
+            count: count
+                .parse()
+                .unwrap_or_else(|_| panic!("Expected gear count to be an integer in {rule}")),
+            aggregate: Aggregate::new(aggregate),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SymbolTotals {
+    symbols: usize,
+    adjacent_numbers: usize,
+    adjacent_sum: i32,
+}
+
+fn symbol_report(schematic: &Schematic) -> BTreeMap<char, SymbolTotals> {
+    let mut report: BTreeMap<char, SymbolTotals> = BTreeMap::new();
+
+    for symbol in &schematic.symbols {
+        let totals = report.entry(symbol.symbol).or_default();
+        totals.symbols += 1;
+
+        for number in schematic.numbers_touching(symbol) {
+            totals.adjacent_numbers += 1;
+            totals.adjacent_sum += number.value;
         }
+    }
+
+    report
+}
 
-        if symbol_found {
-            if let Some((value, start_position)) = number {
-                part_1_result += value;
+const RESET: &str = "\x1b[0m";
+const PART_NUMBER_COLOR: &str = "\x1b[32m";
+const ORPHAN_NUMBER_COLOR: &str = "\x1b[31m";
+const SYMBOL_COLOR: &str = "\x1b[36m";
+const GEAR_COLOR: &str = "\x1b[1;33m";
 
-                parsed_numbers_row.push((value, start_position, schema[i].len() - 1));
+/// Renders the schematic with ANSI colours, listing gear ratios at the end of each row.
+fn render(input: &str, schematic: &Schematic, rule: &GearRule) -> String {
+    let mut result = String::new();
+
+    for (row, line) in input.lines().enumerate() {
+        let mut colors = vec![None; line.chars().count()];
+        let mut ratios = vec![];
+
+        for number in schematic.numbers_by_row[row]
+            .iter()
+            .map(|i| &schematic.numbers[*i])
+        {
+            let color = if schematic.symbols_touching(number).next().is_some() {
+                PART_NUMBER_COLOR
+            } else {
+                ORPHAN_NUMBER_COLOR
+            };
+            colors[number.start..=number.end].fill(Some(color));
+        }
+
+        for symbol in schematic.symbols_by_row[row]
+            .iter()
+            .map(|i| &schematic.symbols[*i])
+        {
+            let ratio = if symbol.symbol == rule.symbol {
+                get_gear_ratio(schematic, symbol, rule)
+            } else {
+                None
+            };
+
+            colors[symbol.col] = Some(if ratio.is_some() {
+                GEAR_COLOR
+            } else {
+                SYMBOL_COLOR
+            });
+
+            if let Some(ratio) = ratio {
+                ratios.push(format!(
+                    "{GEAR_COLOR}{}{RESET}@{}={ratio}",
+                    symbol.symbol, symbol.col
+                ));
             }
         }
 
-        parsed_numbers.push(parsed_numbers_row);
+        for (c, color) in line.chars().zip(colors) {
+            match color {
+                Some(color) => result.push_str(&format!("{color}{c}{RESET}")),
+                None => result.push(c),
+            }
+        }
+
+        if !ratios.is_empty() {
+            result.push_str("  ");
+            result.push_str(&ratios.join(" "));
+        }
+
+        result.push('\n');
     }
 
-    let mut part_2_result = 0;
+    result
+}
+
+fn main() {
+    let mut args = env::args();
+    args.next();
+    let input_file_path = args.next().expect("Input file path");
+
+    let input = fs::read_to_string(&input_file_path)
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}."));
+
+    let schematic = Schematic::new(&input);
 
-    for (i, j) in parsed_gears {
-        if let Some(number) = get_gear_ratio(&parsed_numbers, i, j) {
-            part_2_result += number;
+    match args.next().as_deref() {
+        None => {}
+        Some("gears") => {
+            for rule in args.map(|e| GearRule::new(&e)) {
+                println!(
+                    "Gears {}:{}:{:?} total: {}",
+                    rule.symbol,
+                    rule.count,
+                    rule.aggregate,
+                    gear_total(&schematic, &rule)
+                );
+            }
+            return;
+        }
+        Some("render") => {
+            let rule = args.next().map_or(PART_2_GEAR_RULE, |e| GearRule::new(&e));
+            print!("{}", render(&input, &schematic, &rule));
+            return;
         }
+        Some("report") => {
+            for (symbol, totals) in symbol_report(&schematic) {
+                println!(
+                    "{symbol} symbols: {}, adjacent numbers: {}, adjacent sum: {}",
+                    totals.symbols, totals.adjacent_numbers, totals.adjacent_sum
+                );
+            }
+            return;
+        }
+        Some(other) => panic!("Unexpected mode {other}"),
     }
 
+    let part_1_result: i32 = schematic
+        .numbers
+        .iter()
+        .filter(|e| schematic.symbols_touching(e).next().is_some())
+        .map(|e| e.value)
+        .sum();
+
+    let part_2_result = gear_total(&schematic, &PART_2_GEAR_RULE);
+
     println!("Part 1 result: {part_1_result}");
     println!("Part 2 result: {part_2_result}");
 }
 
-fn get_gear_ratio(
-    parsed_numbers: &Vec<Vec<(i32, usize, usize)>>,
-    i: usize,
-    j: usize,
-) -> Option<i32> {
-    let mut result = vec![];
-
-    for line in (i - 1).max(0)..=(i + 1).min(parsed_numbers.len() - 1) {
-        result.extend(
-            parsed_numbers[line]
-                .iter()
-                .filter(|(_value, start, end)| (start..=end).contains(&&j))
-                .map(|e| e.0),
-        );
-    }
+fn gear_total(schematic: &Schematic, rule: &GearRule) -> i32 {
+    schematic
+        .symbols
+        .iter()
+        .filter(|e| e.symbol == rule.symbol)
+        .filter_map(|e| get_gear_ratio(schematic, e, rule))
+        .sum()
+}
 
-    match result[..] {
-        [first, second] => Some(first * second),
-        _ => None,
+fn get_gear_ratio(schematic: &Schematic, symbol: &Symbol, rule: &GearRule) -> Option<i32> {
+    let result: Vec<_> = schematic
+        .numbers_touching(symbol)
+        .map(|e| e.value)
+        .collect();
+
+    if result.len() != rule.count {
+        return None;
     }
+
+    Some(rule.aggregate.apply(&result))
 }
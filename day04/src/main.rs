@@ -1,55 +1,272 @@
-use std::{env, fs};
+use std::{env, fmt::Display, fs};
 
-fn main() {
-    let mut args = env::args();
-    args.next();
-    let input_file_path = args
-        .next()
-        .expect("Expected input file path as first argument");
+/// Set of card numbers in `0..NumberSet::WIDTH`, one bit per number.
+#[derive(Debug, Clone, Copy, Default)]
+struct NumberSet(u128);
 
-    let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+impl NumberSet {
+    const WIDTH: u32 = u128::BITS;
+
+    /// Inserts the number, returns `false` if it was already present.
+    fn insert(&mut self, number: u32) -> bool {
+        let bit = 1 << number;
+        let inserted = self.0 & bit == 0;
+        self.0 |= bit;
+        inserted
+    }
+
+    fn intersection_len(&self, other: &Self) -> usize {
+        (self.0 & other.0).count_ones() as usize
+    }
+}
+
+#[derive(Debug)]
+enum CardErrorKind {
+    DuplicateWinning(u32),
+    DuplicateDrawn(u32),
+    // kept as text, it may not fit any integer type
+    OutOfRange(String),
+    Unparseable(String),
+}
+
+#[derive(Debug)]
+struct CardError {
+    card_id: usize,
+    kind: CardErrorKind,
+}
 
-    let mut part_1_result = 0;
+impl Display for CardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Card {}: ", self.card_id)?;
+        match &self.kind {
+            CardErrorKind::DuplicateWinning(number) => {
+                write!(f, "duplicate winning number {number}")
+            }
+            CardErrorKind::DuplicateDrawn(number) => write!(f, "duplicate drawn number {number}"),
+            CardErrorKind::OutOfRange(number) => {
+                write!(f, "number {number} out of range 0..{}", NumberSet::WIDTH)
+            }
+            CardErrorKind::Unparseable(token) => write!(f, "unparseable token '{token}'"),
+        }
+    }
+}
 
-    let mut card_counter = vec![1; input.lines().count()];
+#[derive(Debug)]
+struct Card {
+    id: usize,
+    winning_numbers: NumberSet,
+    drawn_numbers: NumberSet,
+}
 
-    for (i, line) in input.lines().enumerate() {
+impl Card {
+    fn new(line: &str) -> Result<Self, Vec<CardError>> {
         let colon_position = line
             .find(": ")
             .expect("Expected card format to include ':'");
 
-        let mut numbers = (&line[colon_position + 2..]).clone().split("|");
+        let id = line[..colon_position]
+            .split(' ')
+            .next_back()
+            .expect("Expected card id before ':'")
+            .parse()
+            .expect("Expected card id to be an integer");
+
+        let mut numbers = line[colon_position + 2..].split('|');
         let winning_numbers = numbers.next().expect("Expected winning numbers before '|'");
         let drawn_numbers = numbers.next().expect("Expected drawn numbers after '|'");
 
-        let winning_numbers: Vec<i32> = winning_numbers
-            .split(' ')
-            .filter_map(|e| e.parse().ok())
-            .collect();
+        let mut errors = vec![];
 
-        let drawn_numbers: Vec<i32> = drawn_numbers
-            .split(' ')
-            .filter_map(|e| e.parse().ok())
-            .collect();
+        let winning_numbers = parse_numbers(
+            winning_numbers,
+            CardErrorKind::DuplicateWinning,
+            &mut errors,
+        );
+        let drawn_numbers =
+            parse_numbers(drawn_numbers, CardErrorKind::DuplicateDrawn, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors
+                .into_iter()
+                .map(|kind| CardError { card_id: id, kind })
+                .collect());
+        }
+
+        Ok(Self {
+            id,
+            winning_numbers,
+            drawn_numbers,
+        })
+    }
 
-        let found_numbers = drawn_numbers
-            .iter()
-            .filter(|e| winning_numbers.contains(e))
-            .count();
+    fn matches(&self) -> usize {
+        self.winning_numbers.intersection_len(&self.drawn_numbers)
+    }
+}
 
-        if found_numbers > 0 {
-            part_1_result += 2i32.pow(found_numbers as u32 - 1);
+fn parse_numbers(
+    numbers: &str,
+    duplicate: fn(u32) -> CardErrorKind,
+    errors: &mut Vec<CardErrorKind>,
+) -> NumberSet {
+    let mut set = NumberSet::default();
+
+    for token in numbers.split(' ').filter(|e| !e.is_empty()) {
+        if !token.chars().all(|e| e.is_ascii_digit()) {
+            errors.push(CardErrorKind::Unparseable(token.to_string()));
+            continue;
         }
 
-        for j in i + 1..(i + 1 + found_numbers).min(card_counter.len()) {
-            card_counter[j] += card_counter[i];
+        let number = match token.parse::<u32>() {
+            Ok(number) if number < NumberSet::WIDTH => number,
+            _ => {
+                errors.push(CardErrorKind::OutOfRange(token.to_string()));
+                continue;
+            }
+        };
+
+        if !set.insert(number) {
+            errors.push(duplicate(number));
+        }
+    }
+
+    set
+}
+
+/// What to do with won copies of cards past the end of the table.
+#[derive(Debug, Clone, Copy)]
+enum OverflowPolicy {
+    Truncate,
+    // Copies wrapped onto already processed cards are counted, but not scratched again.
+    Wrap,
+    Error,
+}
+
+impl OverflowPolicy {
+    fn new(name: &str) -> Option<Self> {
+        match name {
+            "truncate" => Some(Self::Truncate),
+            "wrap" => Some(Self::Wrap),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CardTrace {
+    id: usize,
+    matches: usize,
+    copies_won: usize,
+    instances: usize,
+}
+
+fn cascade(cards: &[Card], policy: OverflowPolicy) -> Vec<CardTrace> {
+    let matches: Vec<_> = cards.iter().map(|e| e.matches()).collect();
+    let mut card_counter = vec![1; cards.len()];
+    let mut copies_won = vec![0; cards.len()];
+
+    for i in 0..cards.len() {
+        if i + matches[i] >= cards.len() {
+            match policy {
+                OverflowPolicy::Truncate | OverflowPolicy::Wrap => {}
+                OverflowPolicy::Error => panic!(
+                    "Card {} wins {} copies, past the last card {}",
+                    cards[i].id,
+                    matches[i],
+                    cards[cards.len() - 1].id
+                ),
+            }
+        }
+
+        let instances = card_counter[i];
+
+        for j in i + 1..=i + matches[i] {
+            let j = match policy {
+                OverflowPolicy::Wrap => j % cards.len(),
+                _ if j >= cards.len() => break,
+                _ => j,
+            };
+
+            card_counter[j] += instances;
+            copies_won[i] += instances;
+        }
+    }
+
+    cards
+        .iter()
+        .zip(matches)
+        .zip(copies_won)
+        .zip(card_counter)
+        .map(|(((card, matches), copies_won), instances)| CardTrace {
+            id: card.id,
+            matches,
+            copies_won,
+            instances,
+        })
+        .collect()
+}
+
+fn main() {
+    let mut args = env::args();
+    args.next();
+    let input_file_path = args
+        .next()
+        .expect("Expected input file path as first argument");
+
+    let mut policy = OverflowPolicy::Truncate;
+    let mut trace = false;
+
+    for arg in args {
+        if arg == "trace" {
+            trace = true;
+        } else {
+            policy =
+                OverflowPolicy::new(&arg).unwrap_or_else(|| panic!("Unexpected argument {arg}"));
+        }
+    }
+
+    let input = fs::read_to_string(&input_file_path)
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
+
+    let mut cards = vec![];
+    let mut errors = vec![];
+
+    for line in input.lines() {
+        match Card::new(line) {
+            Ok(card) => cards.push(card),
+            Err(card_errors) => errors.extend(card_errors),
+        }
+    }
+
+    if !errors.is_empty() {
+        for error in &errors {
+            eprintln!("{error}");
+        }
+        panic!("Found {} invalid card entries", errors.len());
+    }
+
+    let part_1_result: i32 = cards
+        .iter()
+        .map(|e| e.matches())
+        .filter(|e| *e > 0)
+        .map(|e| 2i32.pow(e as u32 - 1))
+        .sum();
+
+    let card_traces = cascade(&cards, policy);
+
+    if trace {
+        for card in &card_traces {
+            println!(
+                "Card {}: matches {}, copies won {}, instances {}",
+                card.id, card.matches, card.copies_won, card.instances
+            );
         }
     }
 
     println!("Part 1 result {part_1_result}");
     println!(
         "Part 2 result {part_2_result}",
-        part_2_result = card_counter.iter().sum::<i32>()
+        part_2_result = card_traces.iter().map(|e| e.instances).sum::<usize>()
     );
 }
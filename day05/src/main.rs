@@ -1,11 +1,243 @@
-use std::{collections::HashMap, env, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+};
 
 #[derive(Debug)]
 struct Mapping {
+    converts_from: String,
     converts_to: String,
     rules: Vec<(i64, i64, i64)>,
 }
 
+/// Piecewise-linear map over `i64`, identity outside of the mapped ranges.
+#[derive(Debug, Clone)]
+struct RangeMap {
+    // (start, offset) sorted by start, each segment ends where the next one starts
+    segments: Vec<(i64, i64)>,
+}
+
+impl RangeMap {
+    fn identity() -> Self {
+        Self {
+            segments: vec![(i64::MIN, 0)],
+        }
+    }
+
+    /// Builds the map from `(destination, source, length)` rules sorted by source.
+    /// Overlapping rules are resolved by first match.
+    fn from_rules(rules: &[(i64, i64, i64)]) -> Self {
+        let mut segments = vec![];
+        let mut covered = i64::MIN;
+
+        for &(destination, source, range) in rules {
+            let start = source.max(covered);
+            let end = source + range;
+
+            if start >= end {
+                continue;
+            }
+
+            segments.push((start, end, destination - source));
+            covered = end;
+        }
+
+        Self::from_segments(segments)
+    }
+
+    /// Builds the map from sorted, non-overlapping `(start, end, offset)` segments.
+    fn from_segments(segments: Vec<(i64, i64, i64)>) -> Self {
+        let mut result: Vec<(i64, i64)> = vec![];
+        let mut position = i64::MIN;
+
+        for (start, end, offset) in segments {
+            if start > position {
+                result.push((position, 0));
+            }
+            result.push((start, offset));
+            position = end;
+        }
+
+        if position < i64::MAX {
+            result.push((position, 0));
+        }
+
+        result.dedup_by(|next, previous| next.1 == previous.1);
+
+        Self { segments: result }
+    }
+
+    fn segment_end(&self, index: usize) -> i64 {
+        self.segments.get(index + 1).map_or(i64::MAX, |e| e.0)
+    }
+
+    fn segment_index(&self, id: i64) -> usize {
+        self.segments.partition_point(|e| e.0 <= id) - 1
+    }
+
+    fn get(&self, id: i64) -> i64 {
+        id + self.segments[self.segment_index(id)].1
+    }
+
+    /// Segments of this map intersecting `start..end`, clipped to it.
+    fn segments_within(&self, start: i64, end: i64) -> impl Iterator<Item = (i64, i64, i64)> + '_ {
+        (self.segment_index(start)..self.segments.len())
+            .take_while(move |i| self.segments[*i].0 < end)
+            .map(move |i| {
+                (
+                    self.segments[i].0.max(start),
+                    self.segment_end(i).min(end),
+                    self.segments[i].1,
+                )
+            })
+    }
+
+    /// Map equivalent to applying `self` and then `next`.
+    fn compose(&self, next: &RangeMap) -> RangeMap {
+        let mut segments = vec![];
+
+        for i in 0..self.segments.len() {
+            let (start, offset) = self.segments[i];
+            let end = self.segment_end(i);
+
+            for (next_start, next_end, next_offset) in
+                next.segments_within(start.saturating_add(offset), end.saturating_add(offset))
+            {
+                segments.push((next_start - offset, next_end - offset, offset + next_offset));
+            }
+        }
+
+        RangeMap::from_segments(segments)
+    }
+
+    /// Segments mapping into the `start..end` range, as `(start, end, offset)` of the source side.
+    fn preimage(&self, start: i64, end: i64) -> Vec<(i64, i64, i64)> {
+        let mut result = vec![];
+
+        for i in 0..self.segments.len() {
+            let (segment_start, offset) = self.segments[i];
+            let segment_end = self.segment_end(i);
+
+            let image_start = segment_start.saturating_add(offset).max(start);
+            let image_end = segment_end.saturating_add(offset).min(end);
+
+            if image_start < image_end {
+                result.push((image_start - offset, image_end - offset, offset));
+            }
+        }
+
+        result
+    }
+
+    /// Smallest mapped value of the `(start, length)` range.
+    fn min_over(&self, (start, range): (i64, i64)) -> Option<i64> {
+        self.segments_within(start, start + range)
+            .map(|(start, _, offset)| start + offset)
+            .min()
+    }
+}
+
+/// Range of length `len` traced through the chain, starting at `starts[i]` in the i-th category.
+#[derive(Debug)]
+struct Trace {
+    len: i64,
+    starts: Vec<i64>,
+}
+
+/// Traces the `start..end` range of the last category back through `range_maps`.
+fn trace_back(range_maps: &[RangeMap], start: i64, end: i64) -> Vec<Trace> {
+    let mut traces = vec![Trace {
+        len: end - start,
+        starts: vec![start],
+    }];
+
+    for range_map in range_maps.iter().rev() {
+        let mut next_traces = vec![];
+
+        for trace in &traces {
+            let head = trace.starts[0];
+
+            for (start, end, offset) in range_map.preimage(head, head + trace.len) {
+                let shift = start + offset - head;
+
+                let mut starts = vec![start];
+                starts.extend(trace.starts.iter().map(|e| e + shift));
+
+                next_traces.push(Trace {
+                    len: end - start,
+                    starts,
+                });
+            }
+        }
+
+        traces = next_traces;
+    }
+
+    traces.sort_unstable_by_key(|e| e.starts[0]);
+    traces
+}
+
+/// Indices of the mappings leading from `source` to `target`.
+fn resolve_chain(
+    map: &HashMap<String, usize>,
+    mappings: &[Mapping],
+    source: &str,
+    target: &str,
+) -> Result<Vec<usize>, String> {
+    let mut chain = vec![];
+    let mut visited = HashSet::new();
+    let mut category = source;
+
+    while category != target {
+        if !visited.insert(category) {
+            return Err(format!("cycle through {category} before reaching {target}"));
+        }
+
+        let Some(mapping_index) = map.get(category) else {
+            return Err(format!("missing mapping from {category} to reach {target}"));
+        };
+
+        chain.push(*mapping_index);
+        category = &mappings[*mapping_index].converts_to;
+    }
+
+    Ok(chain)
+}
+
+/// Rules whose source ranges overlap within one mapping, resolved by first match.
+fn overlapping_rules(mappings: &[Mapping]) -> Vec<String> {
+    let mut result = vec![];
+
+    for mapping in mappings {
+        // rules are sorted by source, so the rule reaching furthest is the only one to check
+        let mut furthest: Option<(i64, i64, i64)> = None;
+
+        for rule in &mapping.rules {
+            if let Some(previous) = furthest {
+                if rule.1 < previous.1 + previous.2 {
+                    result.push(format!(
+                        "Overlapping rules in {}-to-{} map: {} {} {} and {} {} {}",
+                        mapping.converts_from,
+                        mapping.converts_to,
+                        previous.0,
+                        previous.1,
+                        previous.2,
+                        rule.0,
+                        rule.1,
+                        rule.2
+                    ));
+                }
+            }
+
+            if furthest.is_none_or(|e| rule.1 + rule.2 > e.1 + e.2) {
+                furthest = Some(*rule);
+            }
+        }
+    }
+
+    result
+}
+
 fn main() {
     let mut args = env::args();
     args.next();
@@ -14,7 +246,7 @@ fn main() {
         .expect("Expected input file path as first argument");
 
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
 
     let mut blocks = input.split("\n\n");
 
@@ -52,114 +284,105 @@ fn main() {
 
         rules.sort_unstable_by_key(|e| e.1);
 
-        map.insert(mapping_header[0].to_string(), mappings.len());
+        if map
+            .insert(mapping_header[0].to_string(), mappings.len())
+            .is_some()
+        {
+            eprintln!("Duplicate mapping from {}", mapping_header[0]);
+        }
         mappings.push(Mapping {
+            converts_from: mapping_header[0].to_string(),
             converts_to: mapping_header[1].to_string(),
             rules,
         });
     }
 
-    let mut part_1_result = i64::MAX;
-
-    for seed in &seeds {
-        let mut next_mapping = map.get("seed");
-        let mut id = seed.clone();
-
-        while let Some(mapping_index) = next_mapping {
-            let mapping = &mappings[*mapping_index];
-
-            id = convert_by_rules(&mapping.rules, id);
-
-            next_mapping = map.get(&mapping.converts_to);
-        }
-
-        part_1_result = part_1_result.min(id);
-    }
-
-    println!("Part 1 result: {part_1_result}");
-
-    let mut part_2_result = i64::MAX;
-
-    for i in (0..seeds.len()).step_by(2) {
-        // for seed in &seeds {
-        let mut next_mapping = map.get("seed");
-        let mut ranges = vec![(seeds[i], seeds[i + 1])];
-
-        while let Some(mapping_index) = next_mapping {
-            let mapping = &mappings[*mapping_index];
-
-            let mut next_ranges = vec![];
-
-            for range in &ranges {
-                next_ranges.extend_from_slice(&convert_range_by_rules(&mapping.rules, *range));
-            }
-
-            next_mapping = map.get(&mapping.converts_to);
-            ranges = next_ranges;
-        }
-
-        part_2_result = part_2_result.min(
-            ranges
-                .iter()
-                .map(|e| e.0)
-                .min()
-                .expect("Range must contain at least one element"),
-        );
+    for warning in overlapping_rules(&mappings) {
+        eprintln!("{warning}");
     }
 
-    println!("Part 2 result: {part_2_result}");
-}
+    let mut mode = args.next();
 
-fn convert_by_rules(rules: &Vec<(i64, i64, i64)>, id: i64) -> i64 {
-    for &(destination, source, range) in rules {
-        if (source..source + range).contains(&id) {
-            return destination + id - source;
+    let (source, target) = match mode.as_deref().and_then(|e| e.split_once("-to-")) {
+        Some((source, target)) => {
+            let categories = (source.to_string(), target.to_string());
+            mode = args.next();
+            categories
         }
-    }
+        None => ("seed".to_string(), "location".to_string()),
+    };
 
-    id
-}
+    let chain = resolve_chain(&map, &mappings, &source, &target)
+        .unwrap_or_else(|e| panic!("Invalid almanac: {e}"));
 
-fn convert_range_by_rules(rules: &Vec<(i64, i64, i64)>, mut range: (i64, i64)) -> Vec<(i64, i64)> {
-    let mut result = vec![];
+    let mut categories = vec![source.as_str()];
+    let mut range_maps = vec![];
 
-    for (rule_destination, rule_start, rule_len) in rules {
-        let (mut range_start, mut range_len) = range.clone();
+    for mapping_index in chain {
+        let mapping = &mappings[mapping_index];
 
-        if range_start > *rule_start + *rule_len - 1 {
-            // range starts after the rule, continue to the next one
-            continue;
-        }
-
-        if range_start + range_len - 1 < *rule_start {
-            // range ends before the rule, we are done
-            result.push(range);
-            return result;
-        }
+        range_maps.push(RangeMap::from_rules(&mapping.rules));
+        categories.push(&mapping.converts_to);
+    }
 
-        // ranges intersect, split
-        if range_start < *rule_start {
-            let distance = *rule_start - range_start;
-            result.push((range_start, distance));
-            (range_start, range_len) = (*rule_start, range_len - distance);
-        }
+    if let Some(mode) = mode {
+        assert!(mode == "trace", "Unexpected mode {mode}");
 
-        if range_start + range_len <= *rule_start + *rule_len {
-            let distance = range_start - *rule_start;
-            let converted = (*rule_destination + distance, range_len);
+        let start: i64 = args
+            .next()
+            .expect("Expected range start to trace")
+            .parse()
+            .expect("Expected range start to be an integer");
+        let len: i64 = args.next().map_or(1, |e| {
+            e.parse().expect("Expected range length to be an integer")
+        });
 
-            result.push(converted);
-            return result;
+        for trace in trace_back(&range_maps, start, start + len) {
+            // seed ranges only say something about seed ids
+            let in_seeds = source == "seed"
+                && seeds
+                    .chunks_exact(2)
+                    .any(|e| e[0] < trace.starts[0] + trace.len && trace.starts[0] < e[0] + e[1]);
+
+            println!(
+                "{}{}",
+                categories
+                    .iter()
+                    .zip(&trace.starts)
+                    .map(|(category, start)| format!(
+                        "{category} {start}..{}",
+                        start + trace.len - 1
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" -> "),
+                if in_seeds { " (in seed ranges)" } else { "" }
+            );
         }
 
-        let distance = range_start - *rule_start;
-        let converted = (*rule_destination + distance, *rule_len - distance);
-        result.push(converted);
-
-        range = (*rule_start + *rule_len, range_len - (*rule_len - distance));
+        return;
     }
 
-    result.push(range);
-
-    result
+    let range_map = range_maps
+        .iter()
+        .fold(RangeMap::identity(), |acc, e| acc.compose(e));
+
+    let part_1_result = seeds
+        .iter()
+        .map(|e| range_map.get(*e))
+        .min()
+        .expect("Expected at least one seed");
+
+    let part_2_result = seeds
+        .chunks_exact(2)
+        .filter_map(|e| range_map.min_over((e[0], e[1])))
+        .min()
+        .expect("Range must contain at least one element");
+
+    if source == "seed" {
+        println!("Part 1 result: {part_1_result}");
+        println!("Part 2 result: {part_2_result}");
+    } else {
+        println!("Lowest {target} for seed numbers as {source} ids: {part_1_result}");
+        println!("Lowest {target} for seed ranges as {source} ids: {part_2_result}");
+    }
 }
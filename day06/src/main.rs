@@ -1,45 +1,120 @@
-use std::{env, fs};
+use std::{env, fs, ops::RangeInclusive};
 
-fn solve(times: &str, distances: &str) -> i64 {
-    let times: Vec<i64> = times
+/// Boat speed after holding the button, `min(acceleration * hold, max_speed)`.
+#[derive(Debug, Clone, Copy)]
+struct Physics {
+    acceleration: u128,
+    max_speed: Option<u128>,
+}
+
+impl Physics {
+    /// Hold times beating `distance`, i.e. `speed(hold) * (time - hold) > distance`.
+    fn winning_range(&self, time: u128, distance: u128) -> Option<RangeInclusive<u128>> {
+        // acceleration * x > distance is equivalent to x > distance / acceleration
+        let accelerating = winning_range(time, distance / self.acceleration);
+
+        let Some(max_speed) = self.max_speed else {
+            return accelerating;
+        };
+
+        // from this hold time on the speed stays capped and the distance only decreases
+        let capped_from = max_speed.div_ceil(self.acceleration);
+        let capped = time
+            .checked_sub((distance / max_speed).saturating_add(1))
+            .filter(|e| capped_from <= *e)
+            .map(|e| capped_from..=e);
+
+        let accelerating = accelerating.filter(|e| *e.start() < capped_from);
+
+        match (accelerating, capped) {
+            (Some(accelerating), Some(capped)) => Some(*accelerating.start()..=*capped.end()),
+            (Some(accelerating), None) => {
+                Some(*accelerating.start()..=*accelerating.end().min(&(capped_from - 1)))
+            }
+            (None, capped) => capped,
+        }
+    }
+}
+
+/// Winning hold time ranges of each race, `None` for races that can not be won.
+fn solve(times: &str, distances: &str, physics: &Physics) -> Vec<Option<RangeInclusive<u128>>> {
+    let times: Vec<u128> = times
         .split(' ')
-        .filter(|e| *e != "")
-        .filter_map(|e| e.parse().ok())
+        .filter(|e| !e.is_empty())
+        .map(|e| {
+            let time = e
+                .parse()
+                .unwrap_or_else(|_| panic!("Expected time to be an integer, got {e}"));
+            assert!(time < 1 << 64, "Expected time below 2^64, got {e}");
+            time
+        })
         .collect();
-    let distances: Vec<i64> = distances
+    let distances: Vec<u128> = distances
         .split(' ')
-        .filter(|e| *e != "")
-        .filter_map(|e| e.parse().ok())
+        .filter(|e| !e.is_empty())
+        .map(|e| {
+            e.parse()
+                .unwrap_or_else(|_| panic!("Expected distance to fit into u128, got {e}"))
+        })
         .collect();
 
-    let mut part_1_results = vec![];
+    times
+        .iter()
+        .zip(&distances)
+        .map(|(time, distance)| physics.winning_range(*time, *distance))
+        .collect()
+}
+
+fn print_races(part: usize, races: &[Option<RangeInclusive<u128>>]) {
+    let mut ways = vec![];
 
-    for (time, distance) in times.iter().zip(&distances) {
-        let discriminant = time * time - 4 * distance;
-        if discriminant <= 0 {
-            continue;
+    for (i, race) in races.iter().enumerate() {
+        match race {
+            Some(range) => {
+                let count = range.end() - range.start() + 1;
+                println!(
+                    "Part {part} race {}: hold {}..={} ({count} ways)",
+                    i + 1,
+                    range.start(),
+                    range.end()
+                );
+                ways.push(count);
+            }
+            None => println!("Part {part} race {}: can not be won", i + 1),
         }
+    }
 
-        let mut x_1 = ((*time as f64) - (discriminant as f64).sqrt()) / 2.0;
-        let mut x_2 = ((*time as f64) + (discriminant as f64).sqrt()) / 2.0;
+    let result = if ways.is_empty() {
+        0
+    } else {
+        ways.iter().product::<u128>()
+    };
 
-        if x_1 == x_1.ceil() {
-            x_1 += 1.0;
-        }
-        if x_2 == x_2.floor() {
-            x_2 -= 1.0;
-        }
+    println!("Part {part} result {result}");
+}
+
+/// Hold times beating `distance`, i.e. `hold * (time - hold) > distance`. `time` must be below 2^64.
+fn winning_range(time: u128, distance: u128) -> Option<RangeInclusive<u128>> {
+    let beats = |hold: u128| hold * (time - hold) > distance;
 
-        let difference = (x_2.floor() as i64) - (x_1.ceil() as i64) + 1;
+    // a distance of 2^126 or more can not be beaten within 2^64
+    let discriminant = (time * time).checked_sub(distance.checked_mul(4)?)?;
 
-        part_1_results.push(difference);
+    // integer square root is within one of the real root, fix up the rounding
+    let mut first = (time - discriminant.isqrt()) / 2;
+    while first > 0 && beats(first - 1) {
+        first -= 1;
+    }
+    while first <= time / 2 && !beats(first) {
+        first += 1;
     }
 
-    if part_1_results.is_empty() {
-        return 0;
+    if first > time / 2 {
+        return None;
     }
 
-    part_1_results.iter().product::<i64>()
+    // the distance is symmetric around time / 2
+    Some(first..=time - first)
 }
 
 fn main() {
@@ -49,8 +124,22 @@ fn main() {
         .next()
         .expect("Expected input file path as first argument");
 
+    let physics = Physics {
+        acceleration: args.next().map_or(1, |e| {
+            e.parse().expect("Expected acceleration to be an integer")
+        }),
+        max_speed: args
+            .next()
+            .map(|e| e.parse().expect("Expected max speed to be an integer")),
+    };
+    assert!(physics.acceleration > 0, "Expected positive acceleration");
+    assert!(
+        physics.max_speed.is_none_or(|e| e > 0),
+        "Expected positive max speed"
+    );
+
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
 
     let mut lines = input.lines();
     assert!(lines.clone().count() == 2, "Expected two lines in input");
@@ -66,12 +155,43 @@ fn main() {
         "Expected first line to start with 'Distance:'"
     );
 
-    let part_1_result = solve(&times[5..], &distances[9..]);
-    let part_2_result = solve(
-        &times[5..].replace(' ', ""),
-        &distances[9..].replace(' ', ""),
+    print_races(1, &solve(&times[5..], &distances[9..], &physics));
+    print_races(
+        2,
+        &solve(
+            &times[5..].replace(' ', ""),
+            &distances[9..].replace(' ', ""),
+            &physics,
+        ),
     );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Part 1 result {part_1_result}");
-    println!("Part 2 result {part_2_result}");
+    #[test]
+    fn exact_roots_are_excluded() {
+        assert_eq!(winning_range(10, 24), Some(5..=5));
+        assert_eq!(winning_range(30, 200), Some(11..=19));
+    }
+
+    #[test]
+    fn zero_discriminant_can_not_be_won() {
+        assert_eq!(winning_range(10, 25), None);
+    }
+
+    #[test]
+    fn large_time_is_exact() {
+        // the f64 roots counted one way too many here
+        assert_eq!(
+            winning_range(9_007_199_254_740_993, 9_007_199_254_740_992),
+            Some(2..=9_007_199_254_740_991)
+        );
+    }
+
+    #[test]
+    fn huge_distance_can_not_be_won() {
+        assert_eq!(winning_range(1_000_000, u128::MAX), None);
+    }
 }
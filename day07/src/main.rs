@@ -1,132 +1,200 @@
-use std::{cmp::Ordering, env, fs, vec};
+use std::{cmp::Ordering, env, fs};
 
 use itertools::Itertools;
 
 #[derive(Debug, Clone)]
 struct Hand {
-    hand_type: HandType,
+    // index into `RuleSet::categories`, lower is stronger
+    hand_type: usize,
+    // category ignoring wild cards
+    natural_type: usize,
     cards: Vec<usize>,
 }
 
-#[derive(Debug, Copy, Clone)]
-enum HandType {
-    FiveOfAKind,
-    FourOfAKind,
-    FullHouse,
-    ThreeOfAKind,
-    TwoPairs,
-    OnePair,
-    HighCard,
+#[derive(Debug, Clone)]
+struct Category {
+    name: String,
+    // minimal group sizes, largest first
+    groups: Vec<usize>,
 }
 
-impl Hand {
-    fn new(cards: &str, card_order: [char; 13], use_jokers: bool) -> Self {
-        let converted_cards = cards
-            .chars()
+/// Camel Cards variant, see `PART_1_RULES` for the description format.
+#[derive(Debug, Clone)]
+struct RuleSet {
+    // weakest first
+    card_order: Vec<char>,
+    hand_size: usize,
+    wild_cards: Vec<usize>,
+    // strongest first
+    categories: Vec<Category>,
+}
+
+const PART_1_RULES: &str = "\
+cards: 23456789TJQKA
+hand size: 5
+wild:
+category: Five of a kind = 5
+category: Four of a kind = 4
+category: Full house = 3 2
+category: Three of a kind = 3
+category: Two pairs = 2 2
+category: One pair = 2
+category: High card = 1
+";
+
+const PART_2_RULES: &str = "\
+cards: J23456789TQKA
+hand size: 5
+wild: J
+category: Five of a kind = 5
+category: Four of a kind = 4
+category: Full house = 3 2
+category: Three of a kind = 3
+category: Two pairs = 2 2
+category: One pair = 2
+category: High card = 1
+";
+
+impl RuleSet {
+    fn new(description: &str) -> Self {
+        let mut card_order = None;
+        let mut hand_size = None;
+        let mut wild_cards = vec![];
+        let mut categories = vec![];
+
+        for line in description.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once(':')
+                .unwrap_or_else(|| panic!("Expected 'key: value' rule, got {line}"));
+            let value = value.trim();
+
+            match key {
+                "cards" => card_order = Some(value.chars().collect::<Vec<_>>()),
+                "hand size" => {
+                    hand_size = Some(
+                        value
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Expected hand size, got {value}")),
+                    )
+                }
+                "wild" => wild_cards = value.chars().collect(),
+                "category" => {
+                    let (name, groups) = value.split_once(" = ").unwrap_or_else(|| {
+                        panic!("Expected 'name = groups' category, got {value}")
+                    });
+                    assert!(
+                        categories.iter().all(|e: &Category| e.name != name),
+                        "Duplicate category {name}"
+                    );
+
+                    categories.push(Category {
+                        name: name.to_string(),
+                        groups: groups
+                            .split(' ')
+                            .map(|e| {
+                                e.parse()
+                                    .unwrap_or_else(|_| panic!("Expected group size, got {e}"))
+                            })
+                            .collect(),
+                    });
+                }
+                _ => panic!("Unexpected rule {key}"),
+            }
+        }
+
+        let card_order = card_order.expect("Expected 'cards' rule");
+        let wild_cards = wild_cards
+            .iter()
             .map(|e| {
                 card_order
                     .iter()
-                    .position(|c| *c == e)
-                    .expect(&format!("Unexpected card {e}"))
+                    .position(|c| c == e)
+                    .unwrap_or_else(|| panic!("Wild card {e} not in card order"))
             })
             .collect();
 
+        assert!(!categories.is_empty(), "Expected at least one category");
+
         Self {
-            hand_type: HandType::new(&converted_cards, use_jokers),
-            cards: converted_cards,
+            card_order,
+            hand_size: hand_size.expect("Expected 'hand size' rule"),
+            wild_cards,
+            categories,
         }
     }
-}
-
-const PART_1_CARD_ORDER: [char; 13] = [
-    '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'J', 'Q', 'K', 'A',
-];
-
-const PART_2_CARD_ORDER: [char; 13] = [
-    'J', '2', '3', '4', '5', '6', '7', '8', '9', 'T', 'Q', 'K', 'A',
-];
 
-impl HandType {
-    fn new(cards: &Vec<usize>, use_jokers: bool) -> Self {
-        let mut groups: Vec<_> = cards
-            .iter()
-            .sorted()
-            .group_by(|e| e.clone())
-            .into_iter()
-            .map(|(k, v)| (k, v.count()))
+    fn hand(&self, cards: &str) -> Hand {
+        let cards: Vec<_> = cards
+            .chars()
+            .map(|e| {
+                self.card_order
+                    .iter()
+                    .position(|c| *c == e)
+                    .unwrap_or_else(|| panic!("Unexpected card {e}"))
+            })
             .collect();
 
-        groups.sort_by(|a, b| match a.1.cmp(&b.1) {
-            Ordering::Less => Ordering::Greater,
-            Ordering::Equal => b.0.cmp(&a.0),
-            Ordering::Greater => Ordering::Less,
-        });
-
-        let hand_type = match groups[..] {
-            [(_, 5)] => HandType::FiveOfAKind,
-            [(_, 4), (_, 1)] => HandType::FourOfAKind,
-            [(_, 3), (_, 2)] => HandType::FullHouse,
-            [(_, 3), (_, 1), (_, 1)] => HandType::ThreeOfAKind,
-            [(_, 2), (_, 2), (_, 1)] => HandType::TwoPairs,
-            [(_, 2), (_, 1), (_, 1), (_, 1)] => HandType::OnePair,
-            [(_, 1), (_, 1), (_, 1), (_, 1), (_, 1)] => HandType::HighCard,
-            _ => panic!(),
-        };
-
-        if !use_jokers {
-            return hand_type;
-        }
+        assert!(
+            cards.len() == self.hand_size,
+            "Expected {} cards in hand, got {}",
+            self.hand_size,
+            cards.len()
+        );
 
-        if let Some((_, joker_count)) = groups.iter().find(|e| *e.0 == 0) {
-            return match (hand_type, joker_count) {
-                (HandType::FiveOfAKind, 5) => HandType::FiveOfAKind,
-                (HandType::FourOfAKind, 4) => HandType::FiveOfAKind,
-                (HandType::FourOfAKind, 1) => HandType::FiveOfAKind,
-                (HandType::FullHouse, 3) => HandType::FiveOfAKind,
-                (HandType::FullHouse, 2) => HandType::FiveOfAKind,
-                (HandType::ThreeOfAKind, 3) => HandType::FourOfAKind,
-                (HandType::ThreeOfAKind, 1) => HandType::FourOfAKind,
-                (HandType::TwoPairs, 2) => HandType::FourOfAKind,
-                (HandType::TwoPairs, 1) => HandType::FullHouse,
-                (HandType::OnePair, 2) => HandType::ThreeOfAKind,
-                (HandType::OnePair, 1) => HandType::ThreeOfAKind,
-                (HandType::HighCard, 1) => HandType::OnePair,
-                _ => panic!(),
-            };
+        Hand {
+            hand_type: self.hand_type(&groups(&cards, &self.wild_cards)),
+            natural_type: self.hand_type(&groups(&cards, &[])),
+            cards,
         }
+    }
 
-        hand_type
+    /// Strongest category the groups satisfy.
+    fn hand_type(&self, groups: &[usize]) -> usize {
+        self.categories
+            .iter()
+            .position(|category| {
+                category
+                    .groups
+                    .iter()
+                    .enumerate()
+                    .all(|(i, size)| groups.get(i).is_some_and(|e| e >= size))
+            })
+            .unwrap_or_else(|| panic!("No category matches groups {groups:?}"))
     }
 }
 
-impl Ord for Hand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self.hand_type, other.hand_type) {
-            (HandType::FiveOfAKind, HandType::FiveOfAKind)
-            | (HandType::FourOfAKind, HandType::FourOfAKind)
-            | (HandType::FullHouse, HandType::FullHouse)
-            | (HandType::ThreeOfAKind, HandType::ThreeOfAKind)
-            | (HandType::TwoPairs, HandType::TwoPairs)
-            | (HandType::OnePair, HandType::OnePair)
-            | (HandType::HighCard, HandType::HighCard) => self.cards.cmp(&other.cards),
-
-            (HandType::FiveOfAKind, _) => Ordering::Greater,
-            (_, HandType::FiveOfAKind) => Ordering::Less,
-
-            (HandType::FourOfAKind, _) => Ordering::Greater,
-            (_, HandType::FourOfAKind) => Ordering::Less,
-
-            (HandType::FullHouse, _) => Ordering::Greater,
-            (_, HandType::FullHouse) => Ordering::Less,
-
-            (HandType::ThreeOfAKind, _) => Ordering::Greater,
-            (_, HandType::ThreeOfAKind) => Ordering::Less,
+/// Group sizes of the cards, largest first. Wild cards join the largest group.
+fn groups(cards: &[usize], wild_cards: &[usize]) -> Vec<usize> {
+    let mut groups: Vec<_> = cards
+        .iter()
+        .filter(|e| !wild_cards.contains(e))
+        .sorted()
+        .dedup_with_count()
+        .map(|(count, _)| count)
+        .sorted()
+        .rev()
+        .collect();
+
+    let wild_count = cards.iter().filter(|e| wild_cards.contains(e)).count();
+
+    match groups.first_mut() {
+        Some(largest) => *largest += wild_count,
+        None => groups.push(wild_count),
+    }
 
-            (HandType::TwoPairs, _) => Ordering::Greater,
-            (_, HandType::TwoPairs) => Ordering::Less,
+    groups
+}
 
-            (HandType::OnePair, _) => Ordering::Greater,
-            (_, HandType::OnePair) => Ordering::Less,
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match other.hand_type.cmp(&self.hand_type) {
+            Ordering::Equal => self.cards.cmp(&other.cards),
+            ordering => ordering,
         }
     }
 }
@@ -139,16 +207,7 @@ impl PartialOrd for Hand {
 
 impl PartialEq for Hand {
     fn eq(&self, other: &Self) -> bool {
-        match (self.hand_type, other.hand_type) {
-            (HandType::FiveOfAKind, HandType::FiveOfAKind) => self.cards.eq(&other.cards),
-            (HandType::FourOfAKind, HandType::FourOfAKind) => self.cards.eq(&other.cards),
-            (HandType::FullHouse, HandType::FullHouse) => self.cards.eq(&other.cards),
-            (HandType::ThreeOfAKind, HandType::ThreeOfAKind) => self.cards.eq(&other.cards),
-            (HandType::TwoPairs, HandType::TwoPairs) => self.cards.eq(&other.cards),
-            (HandType::OnePair, HandType::OnePair) => self.cards.eq(&other.cards),
-            (HandType::HighCard, HandType::HighCard) => self.cards.eq(&other.cards),
-            _ => false,
-        }
+        self.hand_type == other.hand_type && self.cards == other.cards
     }
 }
 
@@ -161,38 +220,172 @@ fn main() {
         .next()
         .expect("Expected input file path as first argument");
 
+    if input_file_path == "distribution" {
+        let rule_sets: Vec<_> = args
+            .map(|e| {
+                let rule_set = fs::read_to_string(&e)
+                    .unwrap_or_else(|_| panic!("Could not open rule set file {e}"));
+                (e, RuleSet::new(&rule_set))
+            })
+            .collect();
+
+        if rule_sets.is_empty() {
+            print_distribution("Part 1", &RuleSet::new(PART_1_RULES));
+            print_distribution("Part 2", &RuleSet::new(PART_2_RULES));
+        }
+
+        for (rule_set_path, rule_set) in rule_sets {
+            print_distribution(&rule_set_path, &rule_set);
+        }
+
+        return;
+    }
+
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
+
+    let mut args = args.peekable();
+    let report = args.next_if(|e| e == "report").is_some();
 
-    let part_1_result: usize = solve(&input, PART_1_CARD_ORDER, false);
+    let rule_set_paths: Vec<_> = args.collect();
 
-    println!("Part 1 result {part_1_result}");
+    if rule_set_paths.is_empty() {
+        let rule_set = RuleSet::new(PART_1_RULES);
+        let hands = ranked_hands(&input, &rule_set);
+        if report {
+            print_report(&hands, &rule_set);
+        }
+
+        let part_1_result: usize = winnings(&hands);
+
+        println!("Part 1 result {part_1_result}");
+
+        let rule_set = RuleSet::new(PART_2_RULES);
+        let hands = ranked_hands(&input, &rule_set);
+        if report {
+            print_report(&hands, &rule_set);
+        }
 
-    let part_2_result: usize = solve(&input, PART_2_CARD_ORDER, true);
+        let part_2_result: usize = winnings(&hands);
 
-    println!("Part 2 result {part_2_result}");
+        println!("Part 2 result {part_2_result}");
+    }
+
+    for rule_set_path in rule_set_paths {
+        let rule_set = fs::read_to_string(&rule_set_path)
+            .unwrap_or_else(|_| panic!("Could not open rule set file {rule_set_path}"));
+
+        let rule_set = RuleSet::new(&rule_set);
+        let hands = ranked_hands(&input, &rule_set);
+        if report {
+            print_report(&hands, &rule_set);
+        }
+
+        let result = winnings(&hands);
+
+        println!("{rule_set_path} result {result}");
+    }
 }
 
-fn solve(input: &str, card_order: [char; 13], use_jokers: bool) -> usize {
+/// Hands with their bids, weakest first.
+fn ranked_hands<'a>(input: &'a str, rule_set: &RuleSet) -> Vec<(&'a str, Hand, usize)> {
     let mut hands = vec![];
 
     for line in input.lines() {
         let mut line = line.split(' ');
-        let cards = Hand::new(
-            line.next().expect("Expected cards before the first space."),
-            card_order,
-            use_jokers,
-        );
+        let cards = line.next().expect("Expected cards before the first space.");
         let bid: usize = line
             .next()
             .expect("Expected bid after the first space.")
             .parse()
             .expect("Expected bid to be a usize.");
 
-        hands.push((cards, bid));
+        hands.push((cards, rule_set.hand(cards), bid));
     }
 
-    hands.sort_by_cached_key(|e| e.0.clone());
+    hands.sort_by_cached_key(|e| e.1.clone());
 
-    hands.iter().enumerate().map(|(i, e)| (i + 1) * e.1).sum()
+    hands
+}
+
+fn winnings(hands: &[(&str, Hand, usize)]) -> usize {
+    hands.iter().enumerate().map(|(i, e)| (i + 1) * e.2).sum()
+}
+
+fn print_report(hands: &[(&str, Hand, usize)], rule_set: &RuleSet) {
+    for (i, (cards, hand, bid)) in hands.iter().enumerate() {
+        let rank = i + 1;
+
+        print!(
+            "Rank {rank}: {cards} {} -> {}, bid {bid}, contribution {}",
+            rule_set.categories[hand.natural_type].name,
+            rule_set.categories[hand.hand_type].name,
+            rank * bid
+        );
+
+        if i > 0 {
+            let (previous_cards, previous, _) = &hands[i - 1];
+
+            if previous.hand_type != hand.hand_type {
+                print!(", above rank {} by type", rank - 1);
+            } else if let Some(position) =
+                (0..hand.cards.len()).find(|e| hand.cards[*e] != previous.cards[*e])
+            {
+                print!(
+                    ", above rank {} by card {} ({} over {})",
+                    rank - 1,
+                    position + 1,
+                    cards.chars().nth(position).unwrap_or('?'),
+                    previous_cards.chars().nth(position).unwrap_or('?')
+                );
+            } else {
+                print!(", tied with rank {}", rank - 1);
+            }
+        }
+
+        println!();
+    }
+}
+
+/// Number of hands in each category, ignoring and using wild cards, out of all
+/// `card_order.len() ^ hand_size` equally likely hands.
+fn distribution(rule_set: &RuleSet) -> (Vec<u128>, Vec<u128>, u128) {
+    let factorial = |n: usize| (1..=n as u128).product::<u128>();
+
+    let mut natural = vec![0; rule_set.categories.len()];
+    let mut wild = vec![0; rule_set.categories.len()];
+
+    for cards in (0..rule_set.card_order.len()).combinations_with_replacement(rule_set.hand_size) {
+        // number of orderings of this multiset of cards
+        let orderings = factorial(rule_set.hand_size)
+            / cards
+                .iter()
+                .dedup_with_count()
+                .map(|(count, _)| factorial(count))
+                .product::<u128>();
+
+        natural[rule_set.hand_type(&groups(&cards, &[]))] += orderings;
+        wild[rule_set.hand_type(&groups(&cards, &rule_set.wild_cards))] += orderings;
+    }
+
+    let total = (rule_set.card_order.len() as u128).pow(rule_set.hand_size as u32);
+
+    (natural, wild, total)
+}
+
+fn print_distribution(title: &str, rule_set: &RuleSet) {
+    let (natural, wild, total) = distribution(rule_set);
+
+    println!("{title} out of {total} hands");
+
+    for (i, category) in rule_set.categories.iter().enumerate() {
+        println!(
+            "{}: {} ({:.6}), with wild cards {} ({:.6})",
+            category.name,
+            natural[i],
+            natural[i] as f64 / total as f64,
+            wild[i],
+            wild[i] as f64 / total as f64
+        );
+    }
 }
@@ -1,11 +1,12 @@
-use std::{collections::HashMap, env, fs, vec};
+use std::{collections::HashMap, env, fs};
 
-use num::Integer;
+use num::{integer::ExtendedGcd, Integer};
 
-#[derive(Debug)]
+// discriminants index into `Network::links`
+#[derive(Debug, Clone, Copy)]
 enum Instruction {
-    R,
-    L,
+    L = 0,
+    R = 1,
 }
 
 impl Instruction {
@@ -18,6 +19,118 @@ impl Instruction {
     }
 }
 
+/// Fixed size set of node indices.
+#[derive(Debug, Clone)]
+struct BitSet(Vec<u64>);
+
+impl BitSet {
+    fn new(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(64)])
+    }
+
+    fn insert(&mut self, index: u32) {
+        self.0[index as usize / 64] |= 1 << (index % 64);
+    }
+
+    fn contains(&self, index: u32) -> bool {
+        self.0[index as usize / 64] & (1 << (index % 64)) != 0
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.0.len() as u32 * 64).filter(|e| self.contains(*e))
+    }
+}
+
+/// Network with nodes interned to dense indices.
+#[derive(Debug)]
+struct Network {
+    names: Vec<String>,
+    // left and right successor of each node
+    links: Vec<[u32; 2]>,
+}
+
+impl Network {
+    fn new(nodes: &str) -> Self {
+        let nodes: Vec<_> = nodes
+            .lines()
+            .map(|e| {
+                e.split_once(" = ")
+                    .unwrap_or_else(|| panic!("Unexpected node format {e}"))
+            })
+            .collect();
+
+        let node_map: HashMap<&str, u32> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (code, _))| (*code, i as u32))
+            .collect();
+        let index = |code: &str| {
+            *node_map
+                .get(code)
+                .unwrap_or_else(|| panic!("Expected {code} in node map."))
+        };
+
+        let mut names = vec![];
+        let mut links = vec![];
+
+        for (code, node_links) in &nodes {
+            let node_links = node_links.replace(['(', ')'], "");
+            match node_links.split(", ").collect::<Vec<_>>()[..] {
+                [left, right] => links.push([index(left), index(right)]),
+                _ => panic!("Unexpected link format {node_links}"),
+            }
+            names.push(code.to_string());
+        }
+
+        Self { names, links }
+    }
+
+    fn select(&self, predicate: impl Fn(&str) -> bool) -> BitSet {
+        let mut result = BitSet::new(self.names.len());
+
+        for (i, name) in self.names.iter().enumerate() {
+            if predicate(name) {
+                result.insert(i as u32);
+            }
+        }
+
+        result
+    }
+
+    fn index(&self, name: &str) -> u32 {
+        self.names
+            .iter()
+            .position(|e| e == name)
+            .unwrap_or_else(|| panic!("Expected {name} in node map.")) as u32
+    }
+}
+
+/// Matches `*` to any run of characters and `?` to any single character.
+fn glob(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<_> = pattern.chars().collect();
+    let name: Vec<_> = name.chars().collect();
+
+    // matches[j] is whether the pattern prefix processed so far matches name[..j]
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+
+    for p in pattern {
+        let mut next = vec![false; name.len() + 1];
+
+        for j in 0..=name.len() {
+            next[j] = match p {
+                '*' => matches[j] || (j > 0 && next[j - 1]),
+                '?' => j > 0 && matches[j - 1],
+                c => j > 0 && matches[j - 1] && name[j - 1] == c,
+            };
+        }
+
+        matches = next;
+    }
+
+    matches[name.len()]
+}
+
 fn main() {
     let mut args = env::args();
     args.next();
@@ -26,7 +139,7 @@ fn main() {
         .expect("Expected input file path as first argument");
 
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
 
     let mut blocks = input.split("\n\n");
 
@@ -36,67 +149,156 @@ fn main() {
 
     let instructions: Vec<_> = instructions.chars().map(|e| Instruction::new(&e)).collect();
 
-    let nodes = blocks.next().expect("Expected nodes as the second block");
-    let nodes: Vec<_> = nodes
-        .lines()
-        .map(|e| e.split(" = ").collect::<Vec<_>>())
-        .collect();
+    let network = Network::new(blocks.next().expect("Expected nodes as the second block"));
+
+    if let Some(start_pattern) = args.next() {
+        let end_pattern = args
+            .next()
+            .expect("Expected end node pattern after start pattern");
+        let ends = network.select(|e| glob(&end_pattern, e));
 
-    let mut node_map: HashMap<&str, usize> = HashMap::new();
-    let mut node_links = vec![];
+        let mut ghosts = vec![];
 
-    for node in &nodes {
-        match node[..] {
-            [code, links] => {
-                node_map.insert(code, node_links.len());
+        for start in network.select(|e| glob(&start_pattern, e)).iter() {
+            let ghost = walk(&network, &instructions, start, &ends);
 
-                let links = links.replace("(", "").replace(")", "");
-                let links: Vec<String> = links.split(", ").map(|e| e.to_string()).collect();
-                match &links[..] {
-                    [left, right] => node_links.push((left.clone(), right.clone())),
-                    _ => panic!("Unexpected link format {links:?}"),
-                }
+            match first_arrival(std::slice::from_ref(&ghost)) {
+                Some(steps) => println!("{}: {steps} steps", network.names[start as usize]),
+                None => println!("{}: never arrives", network.names[start as usize]),
             }
-            _ => panic!("Unexpected node format {node:?}"),
+
+            ghosts.push(ghost);
+        }
+
+        match first_arrival(&ghosts) {
+            Some(result) => println!("Combined result {result}"),
+            None => println!("Combined result: never arrive at the same time"),
         }
+
+        return;
     }
+    let ends = network.select(|e| e.ends_with('Z'));
 
-    let part_1_result = solve(&node_map, &node_links, &instructions, "AAA");
+    let part_1_result =
+        first_arrival(&[walk(&network, &instructions, network.index("AAA"), &ends)])
+            .expect("Expected AAA to reach an end node");
 
     println!("Part 1 result {part_1_result}");
 
-    let part_2_result: i64 = node_map
-        .keys()
-        .filter(|e| e.ends_with('A'))
-        .map(|e| solve(&node_map, &node_links, &instructions, e))
-        .fold(1, |acc, e| acc.lcm(&e));
+    let ghosts: Vec<_> = network
+        .select(|e| e.ends_with('A'))
+        .iter()
+        .map(|e| walk(&network, &instructions, e, &ends))
+        .collect();
 
-    println!("Part 2 result {part_2_result}");
+    match first_arrival(&ghosts) {
+        Some(part_2_result) => println!("Part 2 result {part_2_result}"),
+        None => println!("Part 2 result: ghosts never arrive at the same time"),
+    }
+}
+
+/// Steps after which a walk stands on an end node. States repeat from `cycle_start` with
+/// period `cycle_len`, so the hits describe the whole infinite walk.
+#[derive(Debug)]
+struct Ghost {
+    // sorted hits in 1..=cycle_start + cycle_len
+    hits: Vec<i128>,
+    cycle_start: i128,
+    cycle_len: i128,
+}
+
+impl Ghost {
+    fn is_hit(&self, step: i128) -> bool {
+        // fold later steps back into the recorded range
+        let step = if step <= self.cycle_start + self.cycle_len {
+            step
+        } else {
+            self.cycle_start + (step - self.cycle_start - 1) % self.cycle_len + 1
+        };
+
+        self.hits.binary_search(&step).is_ok()
+    }
 }
 
-fn solve(
-    node_map: &HashMap<&str, usize>,
-    node_links: &Vec<(String, String)>,
-    instructions: &Vec<Instruction>,
-    start_position: &str,
-) -> i64 {
+fn walk(network: &Network, instructions: &[Instruction], start: u32, ends: &BitSet) -> Ghost {
+    // step at which each node was left at the start of the instructions
+    let mut visited: Vec<Option<i128>> = vec![None; network.names.len()];
+    let mut hits = vec![];
     let mut counter = 0;
-    let mut position = *node_map.get(start_position).unwrap();
+    let mut position = start;
 
-    for instruction in instructions.iter().cycle() {
-        counter += 1;
+    loop {
+        if let Some(cycle_start) = visited[position as usize].replace(counter) {
+            return Ghost {
+                hits,
+                cycle_start,
+                cycle_len: counter - cycle_start,
+            };
+        }
 
-        let next_node = match instruction {
-            Instruction::L => &node_links[position].0,
-            Instruction::R => &node_links[position].1,
-        };
-        if next_node.ends_with('Z') {
-            break;
+        for instruction in instructions {
+            counter += 1;
+
+            position = network.links[position as usize][*instruction as usize];
+            if ends.contains(position) {
+                hits.push(counter);
+            }
         }
-        position = *node_map
-            .get(next_node.as_str())
-            .expect(&format!("Expected {next_node} in node map."));
     }
+}
+
+/// First step at which all ghosts stand on an end node at the same time.
+fn first_arrival(ghosts: &[Ghost]) -> Option<i128> {
+    let last_to_cycle = ghosts.iter().max_by_key(|e| e.cycle_start)?;
+    let cycle_start = last_to_cycle.cycle_start.max(1);
+
+    // before every ghost is in its cycle, the hits of the last one to get there are exhaustive
+    if let Some(step) = last_to_cycle
+        .hits
+        .iter()
+        .take_while(|e| **e < cycle_start)
+        .find(|step| ghosts.iter().all(|e| e.is_hit(**step)))
+    {
+        return Some(*step);
+    }
+
+    // afterwards every ghost hits its cycle residues, combine them with the CRT
+    let mut residues = vec![(0, 1)];
+
+    for ghost in ghosts {
+        let ghost_residues: Vec<_> = ghost
+            .hits
+            .iter()
+            .filter(|e| **e >= ghost.cycle_start)
+            .map(|e| (e % ghost.cycle_len, ghost.cycle_len))
+            .collect();
+
+        residues = residues
+            .iter()
+            .flat_map(|a| ghost_residues.iter().filter_map(|b| crt(*a, *b)))
+            .collect();
+        residues.sort_unstable();
+        residues.dedup();
+    }
+
+    residues
+        .iter()
+        .map(|(residue, modulus)| {
+            residue + (cycle_start - residue + modulus - 1).div_euclid(*modulus) * modulus
+        })
+        .min()
+}
+
+/// Combines `x = a mod m` and `x = b mod n` into `x = c mod lcm(m, n)`, if solvable.
+fn crt((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let ExtendedGcd { gcd, x, .. } = m.extended_gcd(&n);
+
+    if (b - a) % gcd != 0 {
+        return None;
+    }
+
+    let lcm = m / gcd * n;
+    let c = (a + (b - a) / gcd % (n / gcd) * x % (n / gcd) * m).rem_euclid(lcm);
 
-    counter
+    Some((c, lcm))
 }
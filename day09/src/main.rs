@@ -1,53 +1,175 @@
 use std::{env, fs};
 
+/// Edges of the difference table of a history, down to the all-zero row.
+#[derive(Debug)]
+struct History {
+    len: usize,
+    first_entries: Vec<i128>,
+    last_entries: Vec<i128>,
+}
+
+impl History {
+    /// Returns `None` if the differences never reach an all-zero row.
+    fn new(line: &str) -> Option<Self> {
+        let mut line: Vec<i128> = line.split(' ').filter_map(|e| e.parse().ok()).collect();
+
+        assert!(line.len() > 1, "Can not extrapolate with a single entry.");
+
+        let len = line.len();
+        let mut first_entries = vec![];
+        let mut last_entries = vec![];
+
+        while line.iter().any(|e| *e != 0) {
+            if line.len() == 1 {
+                return None;
+            }
+
+            first_entries.push(line[0]);
+            last_entries.push(line[line.len() - 1]);
+
+            line = line.windows(2).map(|e| e[1] - e[0]).collect();
+        }
+
+        Some(Self {
+            len,
+            first_entries,
+            last_entries,
+        })
+    }
+
+    /// Degree of the polynomial generating the history, `None` for all zeros.
+    fn degree(&self) -> Option<usize> {
+        self.first_entries.len().checked_sub(1)
+    }
+
+    /// Value at `position` relative to the first entry, by Newton's forward difference formula
+    /// `sum(binomial(position, j) * first_entries[j])`. Returns `None` on overflow.
+    fn value_at(&self, position: i128) -> Option<i128> {
+        let mut result: i128 = 0;
+        let mut binomial: i128 = 1;
+
+        for (j, entry) in self.first_entries.iter().enumerate() {
+            if j > 0 {
+                // the product of j consecutive integers is divisible by j!, so this is exact
+                binomial = binomial.checked_mul(position - j as i128 + 1)? / j as i128;
+            }
+
+            result = result.checked_add(binomial.checked_mul(*entry)?)?;
+        }
+
+        Some(result)
+    }
+
+    /// Value `steps` after the last entry.
+    fn after(&self, steps: usize) -> Option<i128> {
+        self.value_at(self.len as i128 - 1 + steps as i128)
+    }
+
+    /// Value `steps` before the first entry.
+    fn before(&self, steps: usize) -> Option<i128> {
+        self.value_at(-(steps as i128))
+    }
+
+    /// Next `steps` values after the history.
+    fn forward(&self, steps: usize) -> Vec<i128> {
+        let mut entries = self.last_entries.clone();
+        let mut result = vec![];
+
+        for _ in 0..steps {
+            for i in (0..entries.len().saturating_sub(1)).rev() {
+                entries[i] += entries[i + 1];
+            }
+            result.push(entries.first().copied().unwrap_or(0));
+        }
+
+        result
+    }
+
+    /// Previous `steps` values before the history, nearest first.
+    fn backward(&self, steps: usize) -> Vec<i128> {
+        let mut entries = self.first_entries.clone();
+        let mut result = vec![];
+
+        for _ in 0..steps {
+            for i in (0..entries.len().saturating_sub(1)).rev() {
+                entries[i] -= entries[i + 1];
+            }
+            result.push(entries.first().copied().unwrap_or(0));
+        }
+
+        result
+    }
+}
+
+/// Extrapolations up to this many steps are cross-checked against the difference table.
+const CROSS_CHECK_STEPS: usize = 1_000;
+
 fn main() {
     let mut args = env::args();
     args.next();
     let input_file_path = args
         .next()
         .expect("Expected input file path as first argument");
+    let steps: Option<usize> = args
+        .next()
+        .map(|e| e.parse().expect("Expected number of steps to extrapolate"));
+    assert!(
+        steps != Some(0),
+        "Expected at least one step to extrapolate"
+    );
 
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
-
-    let result: (i32, i32) = input
-        .lines()
-        .map(|line| solve(line))
-        .fold((0, 0), |acc, e| (acc.0 + e.0, acc.1 + e.1));
-
-    println!("Part 1 result {}", result.1);
-    println!("Part 2 result {}", result.0);
-}
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
 
-fn solve(line: &str) -> (i32, i32) {
-    let mut line: Vec<i32> = line.split(' ').filter_map(|e| e.parse().ok()).collect();
+    let report = steps.is_some();
+    let mut result: (i128, i128) = (0, 0);
 
-    assert!(line.len() > 1, "Can not extrapolate with a single entry.");
+    for (i, line) in input.lines().enumerate() {
+        let Some(history) = History::new(line) else {
+            eprintln!("Line {}: differences never reach zero, skipping", i + 1);
+            continue;
+        };
 
-    let mut next_number = line[line.len() - 1];
-    let mut first_entries = vec![line[0]];
+        let steps = steps.unwrap_or(1);
 
-    let mut len = line.len() - 1;
+        let (Some(before), Some(after)) = (history.before(steps), history.after(steps)) else {
+            panic!("Line {}: extrapolation overflows i128", i + 1);
+        };
 
-    loop {
-        let mut all_zero = true;
-
-        for i in 0..len {
-            line[i] = line[i + 1] - line[i];
-            all_zero = all_zero && line[i] == 0;
+        if steps <= CROSS_CHECK_STEPS {
+            assert_eq!(
+                (before, after),
+                (
+                    history.backward(steps)[steps - 1],
+                    history.forward(steps)[steps - 1]
+                ),
+                "Line {}: closed form disagrees with the difference table",
+                i + 1
+            );
         }
 
-        if all_zero {
-            break;
+        if report {
+            println!(
+                "Line {}: degree {}, {steps} before {before}, {steps} after {after}",
+                i + 1,
+                history
+                    .degree()
+                    .map_or("none".to_string(), |e| e.to_string()),
+            );
         }
 
-        len -= 1;
-        next_number += line[len];
-
-        first_entries.push(line[0]);
+        result = (
+            result
+                .0
+                .checked_add(before)
+                .expect("Part 2 result overflows i128"),
+            result
+                .1
+                .checked_add(after)
+                .expect("Part 1 result overflows i128"),
+        );
     }
 
-    let previous_number = first_entries.iter().rev().fold(0, |acc, e| e - acc);
-
-    (previous_number, next_number)
+    println!("Part 1 result {}", result.1);
+    println!("Part 2 result {}", result.0);
 }
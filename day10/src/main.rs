@@ -1,9 +1,8 @@
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     env,
     fmt::{Debug, Display, Write},
     fs,
-    ops::Div,
 };
 
 fn main() {
@@ -13,56 +12,143 @@ fn main() {
         .next()
         .expect("Expected input file path as first argument");
 
+    let mut method = None;
+    // `Some(None)` renders to stdout, `Some(Some(path))` to a file
+    let mut render: Option<Option<String>> = None;
+
+    for arg in args {
+        if arg == "render" {
+            render = Some(None);
+        } else if let Some(path) = arg.strip_prefix("render=") {
+            render = Some(Some(path.to_string()));
+        } else {
+            method = Some(arg);
+        }
+    }
+
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
 
     let map: Vec<Vec<_>> = input
         .lines()
-        .map(|line| line.chars().map(|e| Pipe::new(e)).collect())
+        .map(|line| line.chars().map(Pipe::new).collect())
         .collect();
 
     let bounds = (map.len(), map[0].len());
 
-    let Some(loop_result) = find_loop(&map, &bounds) else {
-        panic!("Loop not found")
-    };
+    let loop_result = find_loop(&map, &bounds).unwrap_or_else(|e| panic!("{e}"));
 
     let clean_map: Vec<Vec<_>> = build_clean_map(&map, &loop_result);
 
-    for line in &clean_map {
-        println!("{}", line.iter().map(|e| e.to_char()).collect::<String>())
+    if let Some(path) = render {
+        let rendered = render_map(&clean_map, loop_result.start_position);
+
+        match path {
+            Some(path) => fs::write(&path, rendered)
+                .unwrap_or_else(|_| panic!("Could not write render to {path}")),
+            None => print!("{rendered}"),
+        }
     }
 
-    let filled_fields = fill(&clean_map, &bounds);
+    let (farthest_position, part_1_result) =
+        farthest_tile(&clean_map, loop_result.start_position, &bounds);
+    let part_2_result = match method.as_deref() {
+        None | Some("flood") => flood_enclosed_tiles(&clean_map, &bounds, &loop_result),
+        Some("shoelace") => enclosed_tiles(&loop_result.vertices),
+        Some("check") => {
+            let flood_result = flood_enclosed_tiles(&clean_map, &bounds, &loop_result);
+            let shoelace_result = enclosed_tiles(&loop_result.vertices);
+            assert_eq!(
+                flood_result, shoelace_result,
+                "Flood fill and Shoelace formula disagree"
+            );
+            shoelace_result
+        }
+        Some(other) => panic!("Unexpected method {other}, expected flood, shoelace or check"),
+    };
+
+    println!(
+        "Part 1 result {} at row {}, col {}",
+        part_1_result, farthest_position.0, farthest_position.1
+    );
+    println!("Part 2 result {}", part_2_result);
+}
+
+const RESET: &str = "\x1b[0m";
+const LOOP_COLOR: &str = "\x1b[36m";
+const START_COLOR: &str = "\x1b[1;31m";
+const INSIDE_COLOR: &str = "\x1b[32m";
+const OUTSIDE_COLOR: &str = "\x1b[2m";
+
+/// Renders the loop with box-drawing characters, shading inside and outside tiles.
+fn render_map(clean_map: &[Vec<Pipe>], start_position: (usize, usize)) -> String {
+    let mut result = String::new();
 
     for (row, line) in clean_map.iter().enumerate() {
+        // a tile is inside if a ray to its left crosses the loop an odd number of times,
+        // counting only pipes connected upwards so that corners are not counted twice
+        let mut inside = false;
+
         for (col, pipe) in line.iter().enumerate() {
-            if filled_fields.contains(&(row, col)) {
-                print!("x")
-            } else {
-                print!("{}", pipe.to_char())
+            if *pipe == Pipe::None {
+                let (color, c) = if inside {
+                    (INSIDE_COLOR, '█')
+                } else {
+                    (OUTSIDE_COLOR, '·')
+                };
+                result.push_str(&format!("{color}{c}{RESET}"));
+                continue;
+            }
+
+            if pipe.connects(Direction::Up) {
+                inside = !inside;
             }
+
+            let color = if (row, col) == start_position {
+                START_COLOR
+            } else {
+                LOOP_COLOR
+            };
+            result.push_str(&format!("{color}{pipe}{RESET}"));
         }
-        println!("")
+
+        result.push('\n');
     }
 
-    let part_1_result = (loop_result.used_positions.len() as f32).div(2.0).ceil() as usize;
-    let part_2_result =
-        bounds.0 * bounds.1 - loop_result.used_positions.len() - filled_fields.len();
+    result
+}
 
-    println!("Part 1 result {}", part_1_result);
-    println!("Part 2 result {}", part_2_result);
+fn flood_enclosed_tiles(
+    clean_map: &[Vec<Pipe>],
+    bounds: &(usize, usize),
+    loop_result: &FindLoopResult,
+) -> usize {
+    let filled_fields = fill(clean_map, bounds);
+
+    bounds.0 * bounds.1 - loop_result.used_positions.len() - filled_fields.len()
 }
 
-fn build_clean_map(map: &Vec<Vec<Pipe>>, find_loop_result: &FindLoopResult) -> Vec<Vec<Pipe>> {
+/// Tiles enclosed by the loop, from its area by the Shoelace formula and Pick's theorem.
+fn enclosed_tiles(vertices: &[(usize, usize)]) -> usize {
+    let double_area = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 as i64 * b.1 as i64 - b.0 as i64 * a.1 as i64)
+        .sum::<i64>()
+        .unsigned_abs() as usize;
+
+    // area = interior + boundary / 2 - 1
+    (double_area + 2 - vertices.len()) / 2
+}
+
+fn build_clean_map(map: &[Vec<Pipe>], find_loop_result: &FindLoopResult) -> Vec<Vec<Pipe>> {
     let mut clean_map: Vec<Vec<_>> = vec![];
 
-    for i in 0..map.len() {
+    for (i, map_row) in map.iter().enumerate() {
         let mut row = vec![];
-        for j in 0..map[i].len() {
-            // todo
+        for (j, pipe) in map_row.iter().enumerate() {
             row.push(if find_loop_result.used_positions.contains(&(i, j)) {
-                map[i][j]
+                *pipe
             } else {
                 Pipe::None
             });
@@ -70,29 +156,13 @@ fn build_clean_map(map: &Vec<Vec<Pipe>>, find_loop_result: &FindLoopResult) -> V
         clean_map.push(row);
     }
 
-    clean_map[find_loop_result.start_position.0][find_loop_result.start_position.1] = match (
-        find_loop_result.start_direction,
-        find_loop_result.end_direction,
-    ) {
-        (Direction::Up, Direction::Up) | (Direction::Down, Direction::Down) => Pipe::Vertical,
-        (Direction::Up, Direction::Left) => Pipe::UpRight,
-        (Direction::Up, Direction::Right) => Pipe::UpLeft,
-        (Direction::Down, Direction::Left) => Pipe::DownRight,
-        (Direction::Down, Direction::Right) => Pipe::DownLeft,
-        (Direction::Left, Direction::Up) => Pipe::DownLeft,
-        (Direction::Left, Direction::Down) => Pipe::UpLeft,
-        (Direction::Left, Direction::Left) | (Direction::Right, Direction::Right) => {
-            Pipe::Horizontal
-        }
-        (Direction::Right, Direction::Up) => Pipe::DownRight,
-        (Direction::Right, Direction::Down) => Pipe::UpRight,
-        _ => panic!("Unexpected start directions"),
-    };
+    clean_map[find_loop_result.start_position.0][find_loop_result.start_position.1] =
+        find_loop_result.start_pipe;
 
     clean_map
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Direction {
     Up,
     Down,
@@ -100,12 +170,24 @@ enum Direction {
     Right,
 }
 
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Left,
+    Direction::Down,
+    Direction::Right,
+];
+
 impl Direction {
-    fn next(
-        self: &Self,
-        position: &(usize, usize),
-        bounds: &(usize, usize),
-    ) -> Option<(usize, usize)> {
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    fn next(&self, position: &(usize, usize), bounds: &(usize, usize)) -> Option<(usize, usize)> {
         match self {
             Direction::Up => {
                 if position.0 == 0 {
@@ -162,7 +244,37 @@ impl Pipe {
         }
     }
 
-    fn next(self: &Self, from_direction: &Direction) -> Option<Direction> {
+    /// Directions the pipe connects to.
+    fn connections(&self) -> Option<[Direction; 2]> {
+        match self {
+            Pipe::Vertical => Some([Direction::Up, Direction::Down]),
+            Pipe::Horizontal => Some([Direction::Left, Direction::Right]),
+            Pipe::UpRight => Some([Direction::Up, Direction::Right]),
+            Pipe::UpLeft => Some([Direction::Up, Direction::Left]),
+            Pipe::DownLeft => Some([Direction::Down, Direction::Left]),
+            Pipe::DownRight => Some([Direction::Down, Direction::Right]),
+            Pipe::None | Pipe::Start => None,
+        }
+    }
+
+    fn from_connections(a: Direction, b: Direction) -> Option<Pipe> {
+        [
+            Pipe::Vertical,
+            Pipe::Horizontal,
+            Pipe::UpRight,
+            Pipe::UpLeft,
+            Pipe::DownLeft,
+            Pipe::DownRight,
+        ]
+        .into_iter()
+        .find(|e| e.connects(a) && e.connects(b) && a != b)
+    }
+
+    fn connects(&self, direction: Direction) -> bool {
+        self.connections().is_some_and(|e| e.contains(&direction))
+    }
+
+    fn next(&self, from_direction: &Direction) -> Option<Direction> {
         match (self, from_direction) {
             (Pipe::Vertical, Direction::Up) => Some(Direction::Up),
             (Pipe::Vertical, Direction::Down) => Some(Direction::Down),
@@ -180,7 +292,7 @@ impl Pipe {
         }
     }
 
-    fn to_char(self: &Self) -> char {
+    fn to_char(self) -> char {
         match self {
             Pipe::Vertical => '│',
             Pipe::Horizontal => '─',
@@ -203,38 +315,97 @@ impl Display for Pipe {
 struct FindLoopResult {
     start_position: (usize, usize),
     used_positions: HashSet<(usize, usize)>,
-    start_direction: Direction,
-    end_direction: Direction,
+    // loop tiles in walking order, ending with the start
+    vertices: Vec<(usize, usize)>,
+    // pipe hidden under the start tile
+    start_pipe: Pipe,
 }
 
-fn find_loop(map: &Vec<Vec<Pipe>>, bounds: &(usize, usize)) -> Option<FindLoopResult> {
-    let Some(start_position) = find_start(map) else {
-        return None;
-    };
+fn find_loop(map: &[Vec<Pipe>], bounds: &(usize, usize)) -> Result<FindLoopResult, String> {
+    let start_position = find_start(map).ok_or("Start tile not found")?;
+
+    // neighbours with a pipe leading back to the start
+    let connected: Vec<_> = DIRECTIONS
+        .into_iter()
+        .filter(|direction| {
+            direction
+                .next(&start_position, bounds)
+                .is_some_and(|(row, col)| map[row][col].connects(direction.opposite()))
+        })
+        .collect();
 
-    for direction in [
-        Direction::Up,
-        Direction::Left,
-        Direction::Down,
-        Direction::Right,
-    ] {
-        if let Some((used_positions, end_direction)) =
-            walk_map(map, start_position, direction.clone(), bounds)
+    let mut loops = vec![];
+
+    for direction in &connected {
+        let Some((vertices, end_direction)) = walk_map(map, start_position, *direction, bounds)
+        else {
+            continue;
+        };
+
+        let start_pipe = Pipe::from_connections(*direction, end_direction.opposite())
+            .ok_or("Loop enters the start tile from the direction it left")?;
+
+        // every loop is found once in each direction
+        if !loops
+            .iter()
+            .any(|e: &FindLoopResult| e.start_pipe == start_pipe)
         {
-            // looks like there is only one valid loop
-            return Some(FindLoopResult {
+            loops.push(FindLoopResult {
                 start_position,
-                used_positions,
-                start_direction: direction,
-                end_direction,
+                used_positions: vertices.iter().copied().collect(),
+                vertices,
+                start_pipe,
             });
         }
     }
 
-    None
+    match loops.len() {
+        0 => Err(format!(
+            "No closed loop through the start tile, connected neighbours {connected:?}"
+        )),
+        1 => Ok(loops.remove(0)),
+        n => Err(format!("{n} different loops pass through the start tile")),
+    }
 }
 
-fn find_start(map: &Vec<Vec<Pipe>>) -> Option<(usize, usize)> {
+/// Tile on the loop farthest from the start, with its distance, by BFS over the clean map.
+fn farthest_tile(
+    clean_map: &[Vec<Pipe>],
+    start_position: (usize, usize),
+    bounds: &(usize, usize),
+) -> ((usize, usize), usize) {
+    let mut distances = HashMap::from([(start_position, 0)]);
+    let mut queue = VecDeque::from([start_position]);
+    let mut farthest = (start_position, 0);
+
+    while let Some(position) = queue.pop_front() {
+        let distance = distances[&position];
+        if distance > farthest.1 {
+            farthest = (position, distance);
+        }
+
+        let Some(connections) = clean_map[position.0][position.1].connections() else {
+            continue;
+        };
+
+        for direction in connections {
+            let Some(next) = direction.next(&position, bounds) else {
+                continue;
+            };
+
+            if clean_map[next.0][next.1].connects(direction.opposite())
+                && !distances.contains_key(&next)
+            {
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    farthest
+}
+
+fn find_start(map: &[Vec<Pipe>]) -> Option<(usize, usize)> {
     for (row_number, row) in map.iter().enumerate() {
         if let Some(col) = row.iter().position(|e| *e == Pipe::Start) {
             return Some((row_number, col));
@@ -245,37 +416,33 @@ fn find_start(map: &Vec<Vec<Pipe>>) -> Option<(usize, usize)> {
 }
 
 fn walk_map(
-    map: &Vec<Vec<Pipe>>,
+    map: &[Vec<Pipe>],
     start_position: (usize, usize),
     start_direction: Direction,
     bounds: &(usize, usize),
-) -> Option<(HashSet<(usize, usize)>, Direction)> {
+) -> Option<(Vec<(usize, usize)>, Direction)> {
     let mut direction = start_direction;
     let mut position = start_position;
-    let mut positions = HashSet::new();
+    let mut positions = vec![];
 
     loop {
-        let Some(next_position) = direction.next(&position, &bounds) else {
-            return None;
-        };
+        let next_position = direction.next(&position, bounds)?;
 
-        positions.insert(next_position);
+        positions.push(next_position);
 
         let pipe = &map[next_position.0][next_position.1];
         if *pipe == Pipe::Start {
             return Some((positions, direction));
         }
 
-        let Some(next_direction) = pipe.next(&direction) else {
-            return None;
-        };
+        let next_direction = pipe.next(&direction)?;
 
         direction = next_direction;
         position = next_position;
     }
 }
 
-fn fill(map: &Vec<Vec<Pipe>>, bounds: &(usize, usize)) -> HashSet<(usize, usize)> {
+fn fill(map: &[Vec<Pipe>], bounds: &(usize, usize)) -> HashSet<(usize, usize)> {
     let mut visited: HashSet<(usize, usize)> = HashSet::new();
     let mut queue: VecDeque<(usize, usize)> = VecDeque::new();
 
@@ -283,11 +450,7 @@ fn fill(map: &Vec<Vec<Pipe>>, bounds: &(usize, usize)) -> HashSet<(usize, usize)
 
     queue.push_back((0, 0));
 
-    loop {
-        let Some(position) = queue.pop_front() else {
-            break
-        };
-
+    while let Some(position) = queue.pop_front() {
         if !visited.insert(position) {
             continue;
         }
@@ -340,10 +503,8 @@ fn fill(map: &Vec<Vec<Pipe>>, bounds: &(usize, usize)) -> HashSet<(usize, usize)
     filled_fields
 }
 
-fn get_field(map: &Vec<Vec<Pipe>>, position: &Option<(usize, usize)>) -> Option<Pipe> {
-    let Some(position) = position else {
-        return None;
-    };
+fn get_field(map: &[Vec<Pipe>], position: &Option<(usize, usize)>) -> Option<Pipe> {
+    let position = (*position)?;
 
     map.get(position.0)?.get(position.1).copied()
 }
@@ -374,23 +535,23 @@ fn get_neigbors(position: &(usize, usize), bounds: &(usize, usize)) -> [Option<(
 }
 
 fn connect_horizontally(left_pipe: Option<Pipe>, right_pipe: Option<Pipe>) -> bool {
-    match (left_pipe, right_pipe) {
-        (None, None) => true,
-        (
-            Some(Pipe::Horizontal) | Some(Pipe::DownRight) | Some(Pipe::UpRight),
-            Some(Pipe::Horizontal) | Some(Pipe::DownLeft) | Some(Pipe::UpLeft),
-        ) => true,
-        _ => false,
-    }
+    matches!(
+        (left_pipe, right_pipe),
+        (None, None)
+            | (
+                Some(Pipe::Horizontal) | Some(Pipe::DownRight) | Some(Pipe::UpRight),
+                Some(Pipe::Horizontal) | Some(Pipe::DownLeft) | Some(Pipe::UpLeft),
+            )
+    )
 }
 
 fn connect_vertically(upper_pipe: Option<Pipe>, lower_pipe: Option<Pipe>) -> bool {
-    match (upper_pipe, lower_pipe) {
-        (None, None) => true,
-        (
-            Some(Pipe::Vertical) | Some(Pipe::DownLeft) | Some(Pipe::DownRight),
-            Some(Pipe::Vertical) | Some(Pipe::UpLeft) | Some(Pipe::UpRight),
-        ) => true,
-        _ => false,
-    }
+    matches!(
+        (upper_pipe, lower_pipe),
+        (None, None)
+            | (
+                Some(Pipe::Vertical) | Some(Pipe::DownLeft) | Some(Pipe::DownRight),
+                Some(Pipe::Vertical) | Some(Pipe::UpLeft) | Some(Pipe::UpRight),
+            )
+    )
 }
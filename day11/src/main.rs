@@ -1,4 +1,160 @@
-use std::{env, fs, vec};
+use std::{collections::BTreeSet, env, fs};
+
+#[derive(Debug)]
+struct Universe {
+    galaxies: Vec<(usize, usize)>,
+    // number of empty rows and columns before each index
+    empty_rows_before: Vec<i64>,
+    empty_cols_before: Vec<i64>,
+}
+
+impl Universe {
+    fn new(input: &str) -> Self {
+        let bounds = (
+            input.lines().count(),
+            input.lines().next().expect("Input must not be empty").len(),
+        );
+
+        let mut galaxies = vec![];
+        let mut empty_rows = vec![true; bounds.0];
+        let mut empty_cols = vec![true; bounds.1];
+
+        for (row, line) in input.lines().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                if c == '#' {
+                    galaxies.push((row, col));
+                    empty_rows[row] = false;
+                    empty_cols[col] = false;
+                }
+            }
+        }
+
+        Self {
+            galaxies,
+            empty_rows_before: prefix_counts(&empty_rows),
+            empty_cols_before: prefix_counts(&empty_cols),
+        }
+    }
+
+    /// Galaxy positions after every empty row and column grew to `factor` rows or columns.
+    fn expand(&self, factor: i64) -> Vec<(i64, i64)> {
+        self.galaxies
+            .iter()
+            .map(|&(row, col)| {
+                (
+                    row as i64 + self.empty_rows_before[row] * (factor - 1),
+                    col as i64 + self.empty_cols_before[col] * (factor - 1),
+                )
+            })
+            .collect()
+    }
+}
+
+fn prefix_counts(empty: &[bool]) -> Vec<i64> {
+    let mut result = vec![0];
+
+    for e in empty {
+        result.push(result[result.len() - 1] + *e as i64);
+    }
+
+    result
+}
+
+fn distance(a: (i64, i64), b: (i64, i64)) -> i64 {
+    (a.0 - b.0).abs() + (a.1 - b.1).abs()
+}
+
+/// Sum of `distance` over all pairs, in O(n log n) by summing each axis separately.
+fn total_distance(galaxies: &[(i64, i64)]) -> i128 {
+    let axis_total = |mut values: Vec<i64>| {
+        values.sort_unstable();
+
+        // every value is subtracted from each larger one, so it contributes with weight 2i - n + 1
+        let n = values.len() as i128;
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, e)| *e as i128 * (2 * i as i128 - n + 1))
+            .sum::<i128>()
+    };
+
+    axis_total(galaxies.iter().map(|e| e.0).collect())
+        + axis_total(galaxies.iter().map(|e| e.1).collect())
+}
+
+/// Closest pair of galaxies as `(distance, i, j)`, by a sweep over the rows keeping galaxies
+/// within the best distance so far ordered by column.
+fn closest_pair(galaxies: &[(i64, i64)]) -> Option<(i64, usize, usize)> {
+    let mut order: Vec<_> = (0..galaxies.len()).collect();
+    order.sort_unstable_by_key(|i| galaxies[*i]);
+
+    let mut best: Option<(i64, usize, usize)> = None;
+    let mut active: BTreeSet<(i64, usize)> = BTreeSet::new();
+    let mut oldest = 0;
+
+    for (position, &i) in order.iter().enumerate() {
+        let (row, col) = galaxies[i];
+        let reach = best.map_or(i64::MAX, |e| e.0);
+
+        while oldest < position && row - galaxies[order[oldest]].0 >= reach {
+            active.remove(&(galaxies[order[oldest]].1, order[oldest]));
+            oldest += 1;
+        }
+
+        for &(_, j) in
+            active.range((col.saturating_sub(reach), 0)..=(col.saturating_add(reach), usize::MAX))
+        {
+            let d = distance(galaxies[i], galaxies[j]);
+            if best.is_none_or(|e| d < e.0) {
+                best = Some((d, j.min(i), j.max(i)));
+            }
+        }
+
+        active.insert((col, i));
+    }
+
+    best
+}
+
+/// Farthest pair of galaxies as `(distance, i, j)`. The Manhattan distance is the larger
+/// spread of `row + col` and `row - col` between the two galaxies.
+fn farthest_pair(galaxies: &[(i64, i64)]) -> Option<(i64, usize, usize)> {
+    if galaxies.len() < 2 {
+        return None;
+    }
+
+    let spread = |key: &dyn Fn((i64, i64)) -> i64| {
+        let min = (0..galaxies.len()).min_by_key(|i| key(galaxies[*i]))?;
+        let max = (0..galaxies.len()).max_by_key(|i| key(galaxies[*i]))?;
+        Some((
+            key(galaxies[max]) - key(galaxies[min]),
+            min.min(max),
+            min.max(max),
+        ))
+    };
+
+    spread(&|e| e.0 + e.1).max(spread(&|e| e.0 - e.1))
+}
+
+/// Distances between every pair of galaxies as CSV, galaxies numbered from 1.
+fn distance_matrix(galaxies: &[(i64, i64)]) -> String {
+    let mut result = String::new();
+
+    for i in 1..=galaxies.len() {
+        result.push_str(&format!(",{i}"));
+    }
+    result.push('\n');
+
+    for (i, a) in galaxies.iter().enumerate() {
+        result.push_str(&(i + 1).to_string());
+        for b in galaxies {
+            result.push_str(&format!(",{}", distance(*a, *b)));
+        }
+        result.push('\n');
+    }
+
+    result
+}
 
 fn main() {
     let mut args = env::args();
@@ -8,50 +164,84 @@ fn main() {
         .expect("Expected input file path as first argument");
 
     let input = fs::read_to_string(&input_file_path)
-        .expect(&format!("Could not open input file {input_file_path}"));
-
-    let bounds = (
-        input.lines().count(),
-        input.lines().next().expect("Input must not be empty").len(),
-    );
-
-    let mut galaxies = vec![];
-    let mut empty_rows = vec![1; bounds.0];
-    let mut empty_cols = vec![1; bounds.1];
-
-    for (row, line) in input.lines().enumerate() {
-        for (col, c) in line.chars().enumerate() {
-            if c == '#' {
-                galaxies.push((row, col));
-                empty_rows[row] = 0;
-                empty_cols[col] = 0;
+        .unwrap_or_else(|_| panic!("Could not open input file {input_file_path}"));
+
+    let universe = Universe::new(&input);
+
+    let mut args = args.peekable();
+    let mode = args.next_if(|e| e.parse::<i64>().is_err());
+
+    let parse_factor = |e: String| {
+        let factor = e
+            .parse()
+            .unwrap_or_else(|_| panic!("Expected expansion factor, got {e}"));
+        assert!(factor >= 1, "Expected expansion factor of at least 1");
+        factor
+    };
+
+    if let Some(mode) = mode {
+        let factor = parse_factor(
+            args.next()
+                .unwrap_or_else(|| panic!("Expected expansion factor after {mode}")),
+        );
+        let galaxies = universe.expand(factor);
+
+        match mode.as_str() {
+            "closest" | "farthest" => {
+                let (label, pair) = if mode == "closest" {
+                    ("Closest", closest_pair(&galaxies))
+                } else {
+                    ("Farthest", farthest_pair(&galaxies))
+                };
+                let (d, i, j) = pair.expect("Expected at least two galaxies");
+                println!("{label} galaxies {} and {} at distance {d}", i + 1, j + 1);
+            }
+            "distance" => {
+                let [i, j] = [args.next(), args.next()].map(|e| {
+                    let index: usize = e
+                        .and_then(|e| e.parse().ok())
+                        .expect("Expected two galaxy numbers after the expansion factor");
+                    assert!(
+                        (1..=galaxies.len()).contains(&index),
+                        "Expected galaxy number in 1..={}, got {index}",
+                        galaxies.len()
+                    );
+                    index
+                });
+                println!(
+                    "Galaxies {i} and {j} at distance {}",
+                    distance(galaxies[i - 1], galaxies[j - 1])
+                );
             }
+            "matrix" => {
+                let matrix = distance_matrix(&galaxies);
+
+                match args.next() {
+                    Some(path) => fs::write(&path, matrix)
+                        .unwrap_or_else(|_| panic!("Could not write matrix to {path}")),
+                    None => print!("{matrix}"),
+                }
+            }
+            _ => panic!("Unexpected mode {mode}"),
         }
+
+        return;
     }
 
-    let mut part_1_result: i64 = 0;
-    let mut part_2_result: i64 = 0;
+    let factors: Vec<i64> = args.map(parse_factor).collect();
 
-    for i in 0..galaxies.len() {
-        for j in i + 1..galaxies.len() {
-            part_1_result += distance(galaxies[i], galaxies[j], &empty_rows, &empty_cols, 1);
-            part_2_result += distance(galaxies[i], galaxies[j], &empty_rows, &empty_cols, 999_999);
-        }
+    if factors.is_empty() {
+        println!("Part 1 result {}", total_distance(&universe.expand(2)));
+        println!(
+            "Part 2 result {}",
+            total_distance(&universe.expand(1_000_000))
+        );
     }
 
-    println!("Part 1 result {part_1_result}");
-    println!("Part 2 result {part_2_result}");
-}
-
-fn distance(
-    a: (usize, usize),
-    b: (usize, usize),
-    empty_rows: &Vec<i64>,
-    empty_cols: &Vec<i64>,
-    coef: i64,
-) -> i64 {
-    (a.0 as i64 - b.0 as i64).abs()
-        + (a.1 as i64 - b.1 as i64).abs()
-        + empty_rows[a.0.min(b.0)..b.0.max(a.0)].iter().sum::<i64>() * coef
-        + empty_cols[a.1.min(b.1)..b.1.max(a.1)].iter().sum::<i64>() * coef
+    for factor in factors {
+        println!(
+            "Factor {factor} result {}",
+            total_distance(&universe.expand(factor))
+        );
+    }
 }